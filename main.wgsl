@@ -37,7 +37,8 @@ struct Uniforms {
     time: f32,
     window_size: vec2<f32>,
     scale: f32,
-    window_scale: f32
+    window_scale: f32,
+    board_size: vec2<f32>,
 };
 
 @group(0) @binding(0)
@@ -133,6 +134,15 @@ fn scene(p: vec3<f32>) -> Surface {
                 res.sd = box.sd;
                 res.col = vec3<f32>(d.shape_data[3], d.shape_data[4], d.shape_data[5]);
             }
+        } else if (d.shape == u32(3)) {
+            // Ghost piece, only the edges of the box are drawn
+            var frame_pos = tetris_pos_to_world_pos(d.position.xy);
+            var frame_size = vec3<f32>(0.125, 0.125, .05);
+            var frame = sdBoxFrame(p - frame_pos, frame_size, 0.01, vec3<f32>(d.shape_data[3], d.shape_data[4], d.shape_data[5]));
+            if (frame.sd < res.sd) {
+                res.sd = frame.sd;
+                res.col = frame.col;
+            }
         }
 
         if (res.sd < 0.001) {
@@ -148,8 +158,8 @@ fn board(p: vec3<f32>) -> Surface {
     var field_size = vec3<f32>(0.125, 0.125, 0.01);
     var b : Surface;
     b.sd = 1000.;
-    for (var i = 0.; i < 10.; i = i + 1.) {
-        for (var j = 0.; j < 20.; j = j + 1.) {
+    for (var i = 0.; i < uniforms.board_size.x; i = i + 1.) {
+        for (var j = 0.; j < uniforms.board_size.y; j = j + 1.) {
             var pos = vec2<f32>(i, j);
             var board_pos = tetris_pos_to_world_pos(pos);
             var board = sdBox(p - board_pos, field_size, vec3<f32>(.25, 0.3, 0.4));
@@ -160,15 +170,16 @@ fn board(p: vec3<f32>) -> Surface {
     return b;
 }
 
-/// 10 x 20 grid for tetris
-/// algined bottom left corner at 0,0
+/// board_size grid for tetris, 10 x 20 by default
+/// algined bottom left corner at 0,0, the board stays centered for other sizes
 /// each block is 0.125 x 0.125
 /// small offset between blocks of 0.025
 fn tetris_pos_to_world_pos(pos: vec2<f32>) -> vec3<f32> {
     // Centered - finally
-    var board_origin = vec3<f32>(-1.5, -3.125, 5.0);
     var grid_field_size = vec3<f32>(0.125, 0.125, 0.0);
     var grid_field_offset = vec3<f32>(0.2, 0.2, 0.0);
+    var size_difference = vec3<f32>(uniforms.board_size - vec2<f32>(10., 20.), 0.0);
+    var board_origin = vec3<f32>(-1.5, -3.125, 5.0) - size_difference * (grid_field_size + grid_field_offset) * .5;
 
     var world_pos = board_origin + vec3<f32>(pos.x, pos.y, 0.0) * grid_field_size + vec3<f32>(pos.x, pos.y, 0.0) * grid_field_offset;
     return world_pos;
@@ -1,7 +1,6 @@
-use std::collections::VecDeque;
-use std::fmt::{Debug, Display, Formatter};
 use bevy::prelude::*;
-use bevy_turborand::{DelegatedRng, GlobalRng};
+use crate::controls::{Action, Controls};
+use tetris_core::{ClearKind, Game, GameConfig, Replay, RotationDirection, Spin, TetrisGame, Tetr, Tetromino, Timeline, TopOut};
 
 #[derive(Resource)]
 pub struct BufferUpdate(pub(crate) bool);
@@ -9,72 +8,238 @@ pub struct BufferUpdate(pub(crate) bool);
 #[derive(Resource)]
 pub struct RenderMarker;
 
-#[derive(Component)]
-pub struct Updated(pub(crate) bool);
-
-#[derive(Component)]
-pub struct Locked;
-
 #[derive(Resource, Default)]
 pub struct Glitch(pub(crate) f32);
 
+#[derive(States, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+    /// Screen for changing the keys of every action
+    Rebinding,
+}
 
-// FIXME: MAYBE SPLIT SHAPE_DATA INTO TWO VEC4s
+/// The running game, all rules live in `tetris_core` and are only driven from here
+#[derive(Resource, Deref, DerefMut)]
+pub struct Tetris(pub Game);
+
+// Everything the game does is sent as one of the events below, right after the tick it happened in,
+// so audio, effects, overlays and stats can react to it without touching the game itself.
+
+#[derive(Event, Debug, Copy, Clone)]
+pub struct PieceSpawned(pub Tetromino);
+
+/// The falling tetromino moved by the given offset, by gravity, shifting or dropping
+#[derive(Event, Debug, Copy, Clone)]
+pub struct PieceMoved {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// `kick` is the index of the used kick, 0 being the basic rotation
+#[derive(Event, Debug, Copy, Clone)]
+pub struct PieceRotated {
+    pub direction: RotationDirection,
+    pub kick: usize,
+}
+
+#[derive(Event, Debug, Copy, Clone)]
+pub struct PieceLocked {
+    pub tetromino: Tetromino,
+    pub spin: Spin,
+}
+
+/// The level reached
+#[derive(Event, Debug, Copy, Clone)]
+pub struct LevelUp(pub u32);
 
-#[derive(Resource, Debug, Default, Clone, Copy)]
-pub struct Score {
-    pub score: u32,
-    pub level: u32,
+/// The tetromino that went into the hold queue
+#[derive(Event, Debug, Copy, Clone)]
+pub struct Hold(pub Tetromino);
+
+#[derive(Event, Debug, Copy, Clone)]
+pub struct GameOver(pub TopOut);
+
+/// Sent to throw away the current game and start a new one
+#[derive(Event, Debug, Copy, Clone)]
+pub struct Restart;
+
+/// Sent whenever full rows were found after a lock, `rows` are the indices before removing them
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct LinesCleared {
+    pub rows: Vec<usize>,
+    pub kind: ClearKind,
+}
+
+/// Sent for every lock that awards points for how it was placed
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Scored(pub tetris_core::Scored);
+
+/// Inputs of the running game so far, only present when recording with `--record`
+#[derive(Resource, Default)]
+pub struct Recording(pub(crate) Option<Replay>);
+
+/// Replay whose inputs drive the game instead of the keyboard, loaded with `--replay`
+#[derive(Resource, Default)]
+pub struct Playback(pub(crate) Option<Viewer>);
+
+/// How a replay is being watched
+pub struct Viewer {
+    pub(crate) timeline: Timeline,
+    pub(crate) paused: bool,
+    /// Ticks played per tick of the fixed timestep
+    pub(crate) speed: f32,
+    /// Ticks owed by the speed that weren't played yet
+    pub(crate) pending: f32,
+    /// Digits typed so far to jump to a piece
+    pub(crate) piece: String,
 }
 
-impl Score {
-    pub fn increase(&mut self, cleared_lines: u32) -> bool {
-        match cleared_lines {
-            1 => self.score += 1,
-            2 => self.score += 3,
-            3 => self.score += 5,
-            4 => self.score += 8,
-            _ => {}
+impl Viewer {
+    pub const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+    pub fn new(replay: Replay) -> Self {
+        Viewer {
+            timeline: Timeline::new(replay),
+            paused: false,
+            speed: 1.0,
+            pending: 0.0,
+            piece: String::new(),
         }
+    }
+
+    /// One line summary for the overlay, e.g. `REPLAY 2X - PIECE 12/80 - TICK 700/4000`
+    pub fn status(&self, game: &Game) -> String {
+        let state = if self.paused { " - PAUSED" } else { "" };
+        let typed = if self.piece.is_empty() { String::new() } else { format!("\nGO TO PIECE {}", self.piece) };
+        format!(
+            "REPLAY {}X{} - PIECE {}/{} - TICK {}/{}{}",
+            self.speed,
+            state,
+            self.timeline.piece_at(game.ticks()),
+            self.timeline.pieces(),
+            game.ticks(),
+            self.timeline.len(),
+            typed
+        )
+    }
+}
 
-        if self.score >= self.goal() {
-            self.score = 0;
-            self.level += 1;
-            return true;
+/// Where the controls screen is at
+#[derive(Resource, Default)]
+pub struct Rebinding {
+    /// Index into [`Action::ALL`]
+    pub(crate) selected: usize,
+    /// Whether the next key pressed gets bound to the selected action
+    pub(crate) waiting: bool,
+    /// State to go back to once the screen is closed
+    pub(crate) back_to: GameState,
+}
+
+impl Rebinding {
+    /// All actions with their keys, the selected one marked
+    pub fn screen(&self, controls: &Controls) -> String {
+        let mut text = String::from("CONTROLS\n");
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let keys = controls.keys(*action).iter().map(|k| format!("{k:?}").to_uppercase());
+            let buttons = controls.buttons(*action).iter().map(|b| format!("PAD {b:?}").to_uppercase());
+            let keys = keys.chain(buttons).collect::<Vec<_>>();
+            let marker = if i == self.selected { "> " } else { "  " };
+            let keys = if i == self.selected && self.waiting { "PRESS A KEY OR BUTTON".to_string() } else { keys.join(", ") };
+            text.push_str(&format!("{marker}{} - {keys}\n", action.name()));
         }
-        false
+        text.push_str("\nENTER - ADD KEY\nBACKSPACE - CLEAR\nESC - SAVE AND BACK");
+        text
     }
+}
+
+/// Counts down before the game resumes from being paused
+#[derive(Resource, Default)]
+pub struct ResumeCountdown(pub(crate) Option<Timer>);
+
+/// Game settings, insert this resource before adding the plugin to change them
+#[derive(Resource, Debug, Clone)]
+pub struct Settings {
+    /// Rules of the game itself
+    pub game: GameConfig,
+    /// Count down from 3 before resuming a paused game
+    pub resume_countdown: bool,
+    /// Show where the falling tetromino would land
+    pub ghost: bool,
+    /// Seed for the randomizer, a random one is picked for every game if not set
+    pub seed: Option<u64>,
+    /// File every game is recorded to, overwritten by the next game
+    pub record: Option<String>,
+    /// File of a replay to watch instead of playing
+    pub replay: Option<String>,
+    /// TOML file the keys of every action are read from, and written to from the controls screen
+    pub controls: String,
+}
 
-    pub fn goal(&self) -> u32 {
-        5 * (self.level + 1)
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            game: GameConfig::default(),
+            resume_countdown: true,
+            ghost: true,
+            seed: None,
+            record: None,
+            replay: None,
+            controls: "controls.toml".to_string(),
+        }
     }
+}
 
-    pub fn timer(&self) -> Timer {
-        match self.level {
-            0 => Timer::from_seconds(1.0, TimerMode::Repeating),
-            1 => Timer::from_seconds(0.79300, TimerMode::Repeating),
-            2 => Timer::from_seconds(0.61780, TimerMode::Repeating),
-            3 => Timer::from_seconds(0.47273, TimerMode::Repeating),
-            4 => Timer::from_seconds(0.35520, TimerMode::Repeating),
-            5 => Timer::from_seconds(0.26200, TimerMode::Repeating),
-            6 => Timer::from_seconds(0.18968, TimerMode::Repeating),
-            7 => Timer::from_seconds(0.13473, TimerMode::Repeating),
-            8 => Timer::from_seconds(0.09388, TimerMode::Repeating),
-            9 => Timer::from_seconds(0.06415, TimerMode::Repeating),
-            10 => Timer::from_seconds(0.04298, TimerMode::Repeating),
-            11 => Timer::from_seconds(0.02822, TimerMode::Repeating),
-            12 => Timer::from_seconds(0.01815, TimerMode::Repeating),
-            13 => Timer::from_seconds(0.01144, TimerMode::Repeating),
-            14 => Timer::from_seconds(0.00706, TimerMode::Repeating),
-            15 => Timer::from_seconds(0.00426, TimerMode::Repeating),
-            16 => Timer::from_seconds(0.00252, TimerMode::Repeating),
-            17 => Timer::from_seconds(0.00146, TimerMode::Repeating),
-            18 => Timer::from_seconds(0.00082, TimerMode::Repeating),
-            19 | _ => Timer::from_seconds(0.00046, TimerMode::Repeating),
+impl Settings {
+    /// Reads the settings from command line arguments like `--lock-down infinite`,
+    /// anything that isn't given (or can't be parsed) keeps its default.
+    pub fn from_args() -> Self {
+        let mut settings = Settings::default();
+        let game = &mut settings.game;
+        let args = std::env::args().collect::<Vec<_>>();
+        for pair in args.windows(2) {
+            match pair[0].as_str() {
+                "--mode" => game.mode = pair[1].parse().unwrap_or(game.mode),
+                "--start-level" => game.start_level = pair[1].parse().map_or(game.start_level, |l| clamp(l, GameConfig::START_LEVELS)),
+                "--lock-down" => game.lock_down = pair[1].parse().unwrap_or(game.lock_down),
+                "--das" => game.das = pair[1].parse().unwrap_or(game.das),
+                "--arr" => game.arr = pair[1].parse().unwrap_or(game.arr),
+                "--das-cut" => game.das_cut = pair[1].parse().unwrap_or(game.das_cut),
+                "--sdf" => game.soft_drop_factor = pair[1].parse().unwrap_or(game.soft_drop_factor),
+                "--resume-countdown" => settings.resume_countdown = pair[1].parse().unwrap_or(settings.resume_countdown),
+                "--ghost" => settings.ghost = pair[1].parse().unwrap_or(settings.ghost),
+                "--randomizer" => game.randomizer = pair[1].parse().unwrap_or(game.randomizer),
+                "--seed" => settings.seed = pair[1].parse().ok().or(settings.seed),
+                "--gravity" => game.gravity = pair[1].parse().ok().or(game.gravity),
+                "--preview" => game.preview = pair[1].parse().map_or(game.preview, |p| clamp(p, GameConfig::PREVIEWS)),
+                "--width" => game.width = pair[1].parse().map_or(game.width, |w| clamp(w, GameConfig::WIDTHS)),
+                "--height" => game.visible_height = pair[1].parse().map_or(game.visible_height, |h| clamp(h, GameConfig::HEIGHTS)),
+                "--are" => game.are = pair[1].parse().ok().or(game.are),
+                "--line-clear-delay" => game.line_clear_delay = pair[1].parse().ok().or(game.line_clear_delay),
+                "--irs" => game.irs = pair[1].parse().unwrap_or(game.irs),
+                "--ihs" => game.ihs = pair[1].parse().unwrap_or(game.ihs),
+                "--tick-rate" => game.tick_rate = pair[1].parse().map_or(game.tick_rate, |r| clamp(r, GameConfig::TICK_RATES)),
+                "--record" => settings.record = Some(pair[1].clone()),
+                "--replay" => settings.replay = Some(pair[1].clone()),
+                "--controls" => settings.controls = pair[1].clone(),
+                "--buffer" => game.buffer_height = pair[1].parse().map_or(game.buffer_height, |h| clamp(h, GameConfig::HEIGHTS)),
+                _ => {}
+            }
         }
+        settings
     }
 }
 
+/// Keeps a setting within the range the game (and replays of it) allow
+fn clamp<T: Ord + Copy>(value: T, range: std::ops::RangeInclusive<T>) -> T {
+    value.clamp(*range.start(), *range.end())
+}
+
+// FIXME: MAYBE SPLIT SHAPE_DATA INTO TWO VEC4s
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Component)]
 pub struct Drawable {
@@ -121,491 +286,61 @@ unsafe impl bytemuck::Zeroable for Drawable {}
 
 unsafe impl bytemuck::Pod for Drawable {}
 
-#[derive(Clone, Debug)]
-pub struct Position {
-    pub x: i32,
-    pub y: i32,
+/// Blocks of the game in the form the shader draws them
+pub trait AsDrawables {
+    fn as_drawables(&self) -> Vec<Drawable>;
 }
 
-#[derive(Debug)]
-pub enum Rotation {
-    Zero = 0,
-    Ninety = 1,
-    OneEighty = 2,
-    TwoHundredSeventy = 3,
+fn block(x: f32, y: f32, size: f32, color: [f32; 3], shape: u32) -> Drawable {
+    let data = [0.125f32 * size, 0.125f32 * size, 0.05f32 * size, 0.0, color[0], color[1], color[2], 0.0];
+    let mut d = Drawable::with_shape_data(0, 0, 6, data, Some(shape));
+    d.position[0] = x;
+    d.position[1] = y;
+    d
 }
 
-// https://tetris.fandom.com/wiki/Tetris_Guideline
-// https://tetris.fandom.com/wiki/SRS
-// We workin' by the Guidelines
-// Therefore no creativity is needed
-
-#[derive(Resource)]
-pub struct TetrisGame {
-    /// Playfield is 10×40, where rows above 20 are hidden or obstructed by the field frame to trick the player into thinking it's 10×20.
-    /// | Guidelines
-    pub field: [[bool; 10]; 40],
-    pub next: Option<Tetromino>,
-    pub hold: Option<Tetromino>,
-    pub score: Score,
-    pub level: u32,
-}
-
-impl Default for TetrisGame {
-    fn default() -> Self {
-        TetrisGame {
-            field: [[false; 10]; 40],
-            next: None,
-            hold: None,
-            score: Score::default(),
-            level: 0,
-        }
-    }
-}
-
-#[derive(Component, Debug, Copy, Clone)]
-pub enum Tetromino {
-    I,
-    O,
-    T,
-    S,
-    Z,
-    J,
-    L,
-}
-
-impl Display for Tetromino {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Tetromino::I => write!(f, "I"),
-            Tetromino::O => write!(f, "O"),
-            Tetromino::T => write!(f, "T"),
-            Tetromino::S => write!(f, "S"),
-            Tetromino::Z => write!(f, "Z"),
-            Tetromino::J => write!(f, "J"),
-            Tetromino::L => write!(f, "L"),
-        }
-    }
-}
-
-impl Tetromino {
-    /// Cyan I,
-    /// Yellow O,
-    /// Purple T,
-    /// Green S,
-    /// Red Z,
-    /// Blue J,
-    /// Orange L
-    /// | Guidelines
-    ///
-    /// RGB format, no need for alpha
-    pub fn color(&self) -> [f32; 3] {
-        match self {
-            Tetromino::I => [0.0, 1.0, 1.0],
-            Tetromino::O => [1.0, 1.0, 0.0],
-            Tetromino::T => [1.0, 0.0, 1.0],
-            Tetromino::S => [0.0, 1.0, 0.0],
-            Tetromino::Z => [1.0, 0.0, 0.0],
-            Tetromino::J => [0.0, 0.0, 1.0],
-            Tetromino::L => [1.0, 0.5, 0.0],
-        }
-    }
-
-    /// Tetromino start locations
-    /// The I and O spawn in the middle columns
-    /// The rest spawn in the left-middle columns
-    /// The tetriminoes spawn horizontally with J, L and T spawning flat-side first.
-    /// Spawn above playfield, row 21 for I, and 21/22 for all other tetriminoes.
-    /// Immediately drop one space if no existing Block is in its path
-    /// | Guidelines
-    // TODO: we can potentially switch the vec for a custom struct, but that's for later
-    //       Okay, maybe we should, as that would be wayyyy easier to work with,
-    //       but that's for later
-    pub fn start_positions(&self) -> Vec<Position> {
-        match self {
-            Tetromino::I => {
-                vec![
-                    Position { x: 3, y: 21 },
-                    Position { x: 4, y: 21 },
-                    Position { x: 5, y: 21 },
-                    Position { x: 6, y: 21 },
-                ]
-            }
-            Tetromino::O => {
-                vec![
-                    Position { x: 4, y: 21 },
-                    Position { x: 5, y: 21 },
-                    Position { x: 4, y: 22 },
-                    Position { x: 5, y: 22 },
-                ]
-            }
-            Tetromino::T => {
-                vec![
-                    Position { x: 3, y: 21 },
-                    Position { x: 4, y: 21 },
-                    Position { x: 5, y: 21 },
-                    Position { x: 4, y: 22 },
-                ]
-            }
-            Tetromino::S => {
-                vec![
-                    Position { x: 3, y: 21 },
-                    Position { x: 4, y: 21 },
-                    Position { x: 4, y: 22 },
-                    Position { x: 5, y: 22 },
-                ]
-            }
-            Tetromino::Z => {
-                vec![
-                    Position { x: 3, y: 22 },
-                    Position { x: 4, y: 22 },
-                    Position { x: 4, y: 21 },
-                    Position { x: 5, y: 21 },
-                ]
-            }
-            Tetromino::J => {
-                vec![
-                    Position { x: 3, y: 22 },
-                    Position { x: 3, y: 21 },
-                    Position { x: 4, y: 21 },
-                    Position { x: 5, y: 21 },
-                ]
-            }
-            Tetromino::L => {
-                vec![
-                    Position { x: 3, y: 21 },
-                    Position { x: 4, y: 21 },
-                    Position { x: 5, y: 21 },
-                    Position { x: 5, y: 22 },
-                ]
-            }
-        }
-    }
-
-    /// Basic Rotation
-    /// The basic rotation states are shown in the diagram on the right. Some points to note:
-    ///
-    /// When unobstructed, the tetrominoes all appear to rotate purely about a single point. These apparent rotation centers are shown as circles in the diagram.
-    /// It is a pure rotation in a mathematical sense.
-    /// As a direct consequence, the J, L, S, T and Z tetrominoes have 1 of their 4 states (the spawn state) in a "floating" position where they are not in contact with the bottom of their bounding box.
-    /// This allows the bounding box to descend below the surface of the stack (or the floor of the playing field) making it impossible for the tetrominoes to be rotated without the aid of floor kicks.
-    /// The S, Z and I tetrominoes have two horizontally oriented states and two vertically oriented states. It can be argued that having two vertical states leads to faster finesse.
-    /// For the "I" and "O" tetrominoes, the apparent rotation center is at the intersection of gridlines, whereas for the "J", "L", "S", "T" and "Z" tetrominoes, the rotation center coincides with the center of one of the four constituent minos.
-    /// | Guidelines
-    pub fn try_basic_rotation(
-        &self,
-        positions: &[Position],
-        current_rotation: &Rotation,
-    ) -> Vec<Position> {
-        let mut new_positions = positions.to_owned();
-        match self {
-            Tetromino::I => {
-                match current_rotation {
-                    Rotation::Zero => {
-                        new_positions[0].x += 2;
-                        new_positions[0].y += 1;
-                        new_positions[1].x += 1;
-                        new_positions[2].y -= 1;
-                        new_positions[3].x -= 1;
-                        new_positions[3].y -= 2;
-                    }
-                    Rotation::Ninety => {
-                        new_positions[0].x += 1;
-                        new_positions[0].y -= 2;
-                        new_positions[1].y -= 1;
-                        new_positions[2].x -= 1;
-                        new_positions[3].x -= 2;
-                        new_positions[3].y += 1;
-                    }
-                    Rotation::OneEighty => {
-                        new_positions[0].x -= 2;
-                        new_positions[0].y -= 1;
-                        new_positions[1].x -= 1;
-                        new_positions[2].y += 1;
-                        new_positions[3].x += 1;
-                        new_positions[3].y += 2;
-                    }
-                    Rotation::TwoHundredSeventy => {
-                        new_positions[0].x -= 1;
-                        new_positions[0].y += 2;
-                        new_positions[1].y += 1;
-                        new_positions[2].x += 1;
-                        new_positions[3].x += 2;
-                        new_positions[3].y -= 1;
-                    }
-                }
-            }
-            Tetromino::O => {}
-            Tetromino::T => {
-                match current_rotation {
-                    Rotation::Zero => {
-                        new_positions[0].x += 1;
-                        new_positions[0].y += 1;
-                        new_positions[2].x -= 1;
-                        new_positions[2].y -= 1;
-                        new_positions[3].x += 1;
-                        new_positions[3].y -= 1;
-                    }
-                    Rotation::Ninety => {
-                        new_positions[0].x += 1;
-                        new_positions[0].y -= 1;
-                        new_positions[2].x -= 1;
-                        new_positions[2].y += 1;
-                        new_positions[3].x -= 1;
-                        new_positions[3].y -= 1;
-                    }
-                    Rotation::OneEighty => {
-                        new_positions[0].x -= 1;
-                        new_positions[0].y -= 1;
-                        new_positions[2].x += 1;
-                        new_positions[2].y += 1;
-                        new_positions[3].x -= 1;
-                        new_positions[3].y += 1;
-                    }
-                    Rotation::TwoHundredSeventy => {
-                        new_positions[0].x -= 1;
-                        new_positions[0].y += 1;
-                        new_positions[2].x += 1;
-                        new_positions[2].y -= 1;
-                        new_positions[3].x += 1;
-                        new_positions[3].y += 1;
-                    }
-                }
-            }
-            Tetromino::S => {
-                match current_rotation {
-                    Rotation::Zero => {
-                        new_positions[0].x += 1;
-                        new_positions[0].y += 1;
-                        new_positions[2].x += 1;
-                        new_positions[2].y -= 1;
-                        new_positions[3].y -= 2;
-                    }
-                    Rotation::Ninety => {
-                        new_positions[0].x += 1;
-                        new_positions[0].y -= 1;
-                        new_positions[2].x -= 1;
-                        new_positions[2].y -= 1;
-                        new_positions[3].x -= 2;
-                    }
-                    Rotation::OneEighty => {
-                        new_positions[0].x -= 1;
-                        new_positions[0].y -= 1;
-                        new_positions[2].x -= 1;
-                        new_positions[2].y += 1;
-                        new_positions[3].y += 2;
-                    }
-                    Rotation::TwoHundredSeventy => {
-                        new_positions[0].x -= 1;
-                        new_positions[0].y += 1;
-                        new_positions[2].x += 1;
-                        new_positions[2].y += 1;
-                        new_positions[3].x += 2;
-                    }
-                }
-            }
-            Tetromino::Z => {
-                match current_rotation {
-                    Rotation::Zero => {
-                        new_positions[0].x += 2;
-                        new_positions[1].x += 1;
-                        new_positions[1].y -= 1;
-                        new_positions[3].x -= 1;
-                        new_positions[3].y -= 1;
-                    }
-                    Rotation::Ninety => {
-                        new_positions[0].y -= 2;
-                        new_positions[1].x -= 1;
-                        new_positions[1].y -= 1;
-                        new_positions[3].x -= 1;
-                        new_positions[3].y += 1;
-                    }
-                    Rotation::OneEighty => {
-                        new_positions[0].x -= 2;
-                        new_positions[1].x -= 1;
-                        new_positions[1].y += 1;
-                        new_positions[3].x += 1;
-                        new_positions[3].y += 1;
-                    }
-                    Rotation::TwoHundredSeventy => {
-                        new_positions[0].y += 2;
-                        new_positions[1].x += 1;
-                        new_positions[1].y += 1;
-                        new_positions[3].x += 1;
-                        new_positions[3].y -= 1;
-                    }
-                }
-            }
-            Tetromino::J => {
-                match current_rotation {
-                    Rotation::Zero => {
-                        new_positions[0].x += 2;
-                        new_positions[1].x += 1;
-                        new_positions[1].y += 1;
-                        new_positions[3].x -= 1;
-                        new_positions[3].y -= 1;
-                    }
-                    Rotation::Ninety => {
-                        new_positions[0].y -= 2;
-                        new_positions[1].x += 1;
-                        new_positions[1].y -= 1;
-                        new_positions[3].x -= 1;
-                        new_positions[3].y += 1;
-                    }
-                    Rotation::OneEighty => {
-                        new_positions[0].x -= 2;
-                        new_positions[1].x -= 1;
-                        new_positions[1].y -= 1;
-                        new_positions[3].x += 1;
-                        new_positions[3].y += 1;
-                    }
-                    Rotation::TwoHundredSeventy => {
-                        new_positions[0].y += 2;
-                        new_positions[1].x -= 1;
-                        new_positions[1].y += 1;
-                        new_positions[3].x += 1;
-                        new_positions[3].y -= 1;
-                    }
-                }
-            }
-            Tetromino::L => {
-                match current_rotation {
-                    Rotation::Zero => {
-                        new_positions[0].x += 1;
-                        new_positions[0].y += 1;
-                        new_positions[2].x -= 1;
-                        new_positions[2].y -= 1;
-                        new_positions[3].y -= 2;
-                    }
-                    Rotation::Ninety => {
-                        new_positions[0].x += 1;
-                        new_positions[0].y -= 1;
-                        new_positions[2].x -= 1;
-                        new_positions[2].y += 1;
-                        new_positions[3].x -= 2;
-                    }
-                    Rotation::OneEighty => {
-                        new_positions[0].x -= 1;
-                        new_positions[0].y -= 1;
-                        new_positions[2].x += 1;
-                        new_positions[2].y += 1;
-                        new_positions[3].y += 2;
-                    }
-                    Rotation::TwoHundredSeventy => {
-                        new_positions[0].x -= 1;
-                        new_positions[0].y += 1;
-                        new_positions[2].x += 1;
-                        new_positions[2].y -= 1;
-                        new_positions[3].x += 2;
-                    }
+impl AsDrawables for TetrisGame {
+    fn as_drawables(&self) -> Vec<Drawable> {
+        let mut drawables = Vec::new();
+        for (y, row) in self.field.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if let Some(color) = cell.color() {
+                    drawables.push(block(x as f32, y as f32, 1.0, color, 2));
                 }
             }
         }
-        new_positions
-    }
-
-    #[allow(dead_code)]
-    pub fn as_drawables(&self) -> Vec<Drawable> {
-        let mut drawables = Vec::new();
-        for position in self.start_positions() {
-            let mut data = vec![0.125f32, 0.125f32, 0.05f32, 0.0, self.color()[0], self.color()[1], self.color()[2]];
-            data.resize(8, 0.0);
-            let d = Drawable::with_shape_data(position.x as isize, position.y as isize, 6, data.try_into().unwrap(), Some(2));
-            drawables.push(d);
-        }
         drawables
     }
 }
 
-#[derive(Component)]
-pub struct Tetr {
-    pub positions: Vec<Position>,
-    pub rotation: Rotation,
-    pub tetromino: Tetromino,
-}
-
-impl Tetr {
-    pub fn new(tetromino: Tetromino) -> Self {
-        let positions = tetromino.start_positions();
-        Tetr {
-            positions,
-            rotation: Rotation::Zero,
-            tetromino,
-        }
-    }
-
-    pub fn as_drawables(&self) -> Vec<Drawable> {
-        let mut drawables = Vec::new();
-        for position in &self.positions {
-            let mut data = vec![0.125f32, 0.125f32, 0.05f32, 0.0, self.tetromino.color()[0], self.tetromino.color()[1], self.tetromino.color()[2]];
-            data.resize(8, 0.0);
-            let d = Drawable::with_shape_data(position.x as isize, position.y as isize, 6, data.try_into().unwrap(), Some(2));
-            drawables.push(d);
-        }
-        drawables
-    }
-
-    pub fn offset(&self) -> u64 {
-        (std::mem::size_of::<Drawable>() * self.positions.len()) as u64
-    }
-
-    pub fn spin(&mut self) {
-        self.positions = self.tetromino.try_basic_rotation(self.positions.as_slice(), &self.rotation);
-        while self.positions.iter().any(|p| p.x < 0) {
-            self.positions.iter_mut().for_each(|p| p.x += 1);
-        }
-        while self.positions.iter().any(|p| p.x > 9) {
-            self.positions.iter_mut().for_each(|p| p.x -= 1);
-        }
-
-        self.rotation = match self.rotation {
-            Rotation::Zero => Rotation::Ninety,
-            Rotation::Ninety => Rotation::OneEighty,
-            Rotation::OneEighty => Rotation::TwoHundredSeventy,
-            Rotation::TwoHundredSeventy => Rotation::Zero,
-        }
+impl AsDrawables for Tetr {
+    fn as_drawables(&self) -> Vec<Drawable> {
+        self.positions
+            .iter()
+            .map(|p| block(p.x as f32, p.y as f32, 1.0, self.tetromino.color(), 2))
+            .collect()
     }
 }
 
-#[derive(Resource)]
-pub struct TetroQueue {
-    queue: VecDeque<Tetromino>
+/// Outline of where the tetromino would land, drawn as a darker frame
+pub fn ghost_drawables(tetr: &Tetr, game: &TetrisGame) -> Vec<Drawable> {
+    let color = tetr.tetromino.color().map(|c| c * 0.5);
+    tetr.ghost_positions(game)
+        .iter()
+        .map(|p| {
+            let mut d = block(p.x as f32, p.y as f32, 1.0, color, 3);
+            d.shape_data[3] = 0.01;
+            d
+        })
+        .collect()
 }
 
-impl Default for TetroQueue {
-    fn default() -> Self {
-        TetroQueue {
-            queue: VecDeque::new()
-        }
-    }
-
+/// Draws the tetromino in its spawn orientation with the bottom left of its bounding box at `x`, `y`,
+/// `scale` shrinks both the blocks and the distance between them for miniatures.
+pub fn preview_drawables(tetromino: Tetromino, x: f32, y: f32, scale: f32) -> Vec<Drawable> {
+    tetromino
+        .start_positions()
+        .iter()
+        .map(|p| block(x + (p.x - 3) as f32 * scale, y + (p.y - 21) as f32 * scale, scale, tetromino.color(), 2))
+        .collect()
 }
-
-impl TetroQueue {
-
-    pub fn len(&self) -> usize {
-        self.queue.len()
-    }
-
-    pub fn push(&mut self, tetromino: Tetromino) {
-        self.queue.push_back(tetromino);
-    }
-
-    pub fn pop(&mut self) -> Option<Tetromino> {
-        self.queue.pop_front()
-    }
-
-    pub fn get(&self, index: usize) -> Option<&Tetromino> {
-        self.queue.get(index)
-    }
-
-
-    pub fn fill_queue(&mut self, mut rng: &mut GlobalRng) {
-        let mut bag = vec![Tetromino::I, Tetromino::O, Tetromino::T, Tetromino::S, Tetromino::Z, Tetromino::J, Tetromino::L];
-        for _ in 0..7 {
-            let index = rng.u8(0..bag.len() as u8) as usize;
-            let tetromino = bag.remove(index);
-            self.push(tetromino);
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::{Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType};
+use bevy::input::{Axis, ButtonInput};
+use bevy::prelude::{KeyCode, Res, Resource};
+use serde::{Deserialize, Serialize};
+use tetris_core::Inputs;
+
+/// Everything a player can do, each bound to any number of keys and gamepad buttons
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    #[serde(rename = "rotate_cw")]
+    RotateCW,
+    #[serde(rename = "rotate_ccw")]
+    RotateCCW,
+    #[serde(rename = "rotate_180")]
+    Rotate180,
+    Hold,
+    Pause,
+    Restart,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateCW,
+        Action::RotateCCW,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+        Action::Restart,
+    ];
+
+    /// The button of the game this action holds, `None` for actions outside of the game itself
+    pub fn button(&self) -> Option<Inputs> {
+        match self {
+            Action::MoveLeft => Some(Inputs::LEFT),
+            Action::MoveRight => Some(Inputs::RIGHT),
+            Action::SoftDrop => Some(Inputs::SOFT_DROP),
+            Action::HardDrop => Some(Inputs::HARD_DROP),
+            Action::RotateCW => Some(Inputs::ROTATE_CW),
+            Action::RotateCCW => Some(Inputs::ROTATE_CCW),
+            Action::Rotate180 => Some(Inputs::ROTATE_180),
+            Action::Hold => Some(Inputs::HOLD),
+            Action::Pause | Action::Restart => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::SoftDrop => "SOFT DROP",
+            Action::HardDrop => "HARD DROP",
+            Action::RotateCW => "ROTATE CW",
+            Action::RotateCCW => "ROTATE CCW",
+            Action::Rotate180 => "ROTATE 180",
+            Action::Hold => "HOLD",
+            Action::Pause => "PAUSE",
+            Action::Restart => "RESTART",
+        }
+    }
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            Action::MoveLeft => vec![KeyCode::ArrowLeft],
+            Action::MoveRight => vec![KeyCode::ArrowRight],
+            Action::SoftDrop => vec![KeyCode::ArrowDown],
+            Action::HardDrop => vec![KeyCode::Space],
+            Action::RotateCW => vec![KeyCode::ArrowUp],
+            Action::RotateCCW => vec![KeyCode::KeyZ, KeyCode::ControlLeft, KeyCode::ControlRight],
+            Action::Rotate180 => vec![KeyCode::KeyA],
+            Action::Hold => vec![KeyCode::KeyC, KeyCode::ShiftLeft, KeyCode::ShiftRight],
+            Action::Pause => vec![KeyCode::Escape, KeyCode::KeyP],
+            Action::Restart => vec![KeyCode::KeyR],
+        }
+    }
+
+    fn default_buttons(&self) -> Vec<GamepadButtonType> {
+        match self {
+            Action::MoveLeft => vec![GamepadButtonType::DPadLeft],
+            Action::MoveRight => vec![GamepadButtonType::DPadRight],
+            Action::SoftDrop => vec![GamepadButtonType::DPadDown],
+            Action::HardDrop => vec![GamepadButtonType::DPadUp],
+            Action::RotateCW => vec![GamepadButtonType::East],
+            Action::RotateCCW => vec![GamepadButtonType::South],
+            Action::Rotate180 => vec![GamepadButtonType::North],
+            Action::Hold => vec![GamepadButtonType::LeftTrigger, GamepadButtonType::RightTrigger],
+            Action::Pause => vec![GamepadButtonType::Start],
+            Action::Restart => vec![GamepadButtonType::Select],
+        }
+    }
+}
+
+/// Keys and gamepad buttons bound to every action, read from and written back to a TOML file like
+///
+/// ```toml
+/// deadzone = 0.5
+///
+/// [keys]
+/// move_left = ["ArrowLeft"]
+/// rotate_ccw = ["KeyZ", "ControlLeft"]
+///
+/// [buttons]
+/// move_left = ["DPadLeft"]
+/// ```
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Controls {
+    /// How far the left stick has to be pushed before it moves the tetromino, from 0 to 1
+    pub deadzone: f32,
+    pub keys: BTreeMap<Action, Vec<KeyCode>>,
+    pub buttons: BTreeMap<Action, Vec<GamepadButtonType>>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls {
+            deadzone: 0.5,
+            keys: Action::ALL.iter().map(|a| (*a, a.default_keys())).collect(),
+            buttons: Action::ALL.iter().map(|a| (*a, a.default_buttons())).collect(),
+        }
+    }
+}
+
+impl Controls {
+    /// Reads the controls from the file, actions missing in it keep their default keys and buttons
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+        let mut controls: Controls = toml::from_str(&text).map_err(|e| format!("can't load {path}: {e}"))?;
+        for action in Action::ALL {
+            controls.keys.entry(action).or_insert_with(|| action.default_keys());
+            controls.buttons.entry(action).or_insert_with(|| action.default_buttons());
+        }
+        controls.deadzone = controls.deadzone.clamp(0.0, 1.0);
+        Ok(controls)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| format!("can't save controls: {e}"))?;
+        std::fs::write(path, text).map_err(|e| format!("can't write {path}: {e}"))
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.buttons.get(&action).map_or(&[], |buttons| buttons.as_slice())
+    }
+
+    /// Buttons of the game held on the keyboard
+    pub fn keyboard_inputs(&self, keyboard: &ButtonInput<KeyCode>) -> Inputs {
+        let mut inputs = Inputs::NONE;
+        for action in Action::ALL {
+            if let Some(button) = action.button().filter(|_| keyboard.any_pressed(self.keys(action).iter().copied())) {
+                inputs.insert(button);
+            }
+        }
+        inputs
+    }
+
+    /// Buttons of the game held on the gamepad, the left stick moves and soft drops like the D-pad
+    pub fn gamepad_inputs(&self, gamepad: Gamepad, buttons: &ButtonInput<GamepadButton>, axes: &Axis<GamepadAxis>) -> Inputs {
+        let mut inputs = Inputs::NONE;
+        for action in Action::ALL {
+            let mut held = self.buttons(action).iter().map(|b| GamepadButton::new(gamepad, *b));
+            if let Some(button) = action.button().filter(|_| held.any(|b| buttons.pressed(b))) {
+                inputs.insert(button);
+            }
+        }
+
+        let x = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+        let y = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+        if x <= -self.deadzone {
+            inputs.insert(Inputs::LEFT);
+        }
+        if x >= self.deadzone {
+            inputs.insert(Inputs::RIGHT);
+        }
+        // Pushing up doesn't hard drop, that happens by accident too easily
+        if y <= -self.deadzone {
+            inputs.insert(Inputs::SOFT_DROP);
+        }
+        inputs
+    }
+
+    /// Adds the key to the action, taking it away from any other action so one key never does two things
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        for keys in self.keys.values_mut() {
+            keys.retain(|k| *k != key);
+        }
+        self.keys.entry(action).or_default().push(key);
+    }
+
+    /// Same as [`Controls::bind`] for a gamepad button
+    pub fn bind_button(&mut self, action: Action, button: GamepadButtonType) {
+        for buttons in self.buttons.values_mut() {
+            buttons.retain(|b| *b != button);
+        }
+        self.buttons.entry(action).or_default().push(button);
+    }
+
+    /// Removes all keys and buttons of the action
+    pub fn clear(&mut self, action: Action) {
+        self.keys.entry(action).or_default().clear();
+        self.buttons.entry(action).or_default().clear();
+    }
+}
+
+/// Gamepad of every player for local multiplayer, handed out in the order gamepads get connected
+#[derive(Resource, Debug, Default)]
+pub struct Players(pub [Option<Gamepad>; Players::MAX]);
+
+impl Players {
+    pub const MAX: usize = 4;
+
+    pub fn gamepad(&self, player: usize) -> Option<Gamepad> {
+        self.0.get(player).copied().flatten()
+    }
+
+    /// Gives the gamepad to the first player without one, returns that player
+    pub fn connect(&mut self, gamepad: Gamepad) -> Option<usize> {
+        if let Some(player) = self.0.iter().position(|p| *p == Some(gamepad)) {
+            return Some(player);
+        }
+        let player = self.0.iter().position(|p| p.is_none())?;
+        self.0[player] = Some(gamepad);
+        Some(player)
+    }
+
+    /// Takes the gamepad away from its player, returns the player that lost it
+    pub fn disconnect(&mut self, gamepad: Gamepad) -> Option<usize> {
+        let player = self.0.iter().position(|p| *p == Some(gamepad))?;
+        self.0[player] = None;
+        Some(player)
+    }
+}
+
+/// Everything actions are read from: the keyboard, the gamepads and which player holds which gamepad
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    pub keyboard: Res<'w, ButtonInput<KeyCode>>,
+    pub buttons: Res<'w, ButtonInput<GamepadButton>>,
+    pub axes: Res<'w, Axis<GamepadAxis>>,
+    pub controls: Res<'w, Controls>,
+    pub players: Res<'w, Players>,
+}
+
+impl ActionInput<'_> {
+    /// Whether the player just pressed the action, on the keyboard for the first player or on their gamepad
+    pub fn just_pressed(&self, player: usize, action: Action) -> bool {
+        let keyboard = player == 0 && self.keyboard.any_just_pressed(self.controls.keys(action).iter().copied());
+        keyboard || self.gamepad_just_pressed(player, |button| self.controls.buttons(action).contains(&button))
+    }
+
+    /// Enter on the keyboard for the first player or Start on their gamepad, for leaving the menus
+    pub fn confirm(&self, player: usize) -> bool {
+        let keyboard = player == 0 && self.keyboard.just_pressed(KeyCode::Enter);
+        keyboard || self.gamepad_just_pressed(player, |button| button == GamepadButtonType::Start)
+    }
+
+    /// Whether a button was just pressed on the gamepad of the player, gamepads nobody plays with are ignored
+    fn gamepad_just_pressed(&self, player: usize, matches: impl Fn(GamepadButtonType) -> bool) -> bool {
+        let Some(gamepad) = self.players.gamepad(player) else {
+            return false;
+        };
+        self.buttons.get_just_pressed().any(|b| b.gamepad == gamepad && matches(b.button_type))
+    }
+
+    /// Buttons of the game held by the player, the first player also plays on the keyboard
+    pub fn inputs(&self, player: usize) -> Inputs {
+        let mut inputs = match player {
+            0 => self.controls.keyboard_inputs(&self.keyboard),
+            _ => Inputs::NONE,
+        };
+        if let Some(gamepad) = self.players.gamepad(player) {
+            inputs = inputs | self.controls.gamepad_inputs(gamepad, &self.buttons, &self.axes);
+        }
+        inputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::gamepad::{GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent, GamepadInfo};
+    use bevy::input::InputPlugin;
+    use bevy::prelude::{App, ResMut, Update};
+    use bevy::MinimalPlugins;
+
+    /// What the first player did in the last update
+    #[derive(Resource, Default)]
+    struct Read {
+        inputs: Inputs,
+        pause: bool,
+        confirm: bool,
+    }
+
+    fn read(actions: ActionInput, mut read: ResMut<Read>) {
+        *read = Read {
+            inputs: actions.inputs(0),
+            pause: actions.just_pressed(0, Action::Pause),
+            confirm: actions.confirm(0),
+        };
+    }
+
+    /// A headless app with two connected gamepads, only the first one is given to a player
+    fn app() -> (App, Gamepad, Gamepad) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin))
+            .init_resource::<Controls>()
+            .init_resource::<Players>()
+            .init_resource::<Read>()
+            .add_systems(Update, read);
+        let (assigned, unassigned) = (Gamepad::new(0), Gamepad::new(1));
+        for gamepad in [assigned, unassigned] {
+            let info = GamepadInfo { name: "test".to_string() };
+            app.world.send_event(GamepadConnectionEvent::new(gamepad, GamepadConnection::Connected(info)));
+        }
+        app.world.resource_mut::<Players>().connect(assigned);
+        app.update();
+        (app, assigned, unassigned)
+    }
+
+    fn press(app: &mut App, gamepad: Gamepad, button: GamepadButtonType, value: f32) {
+        app.world.send_event(GamepadButtonChangedEvent::new(gamepad, button, value));
+        app.update();
+    }
+
+    #[test]
+    fn gamepad_buttons_hold_game_buttons() {
+        let (mut app, gamepad, _) = app();
+        press(&mut app, gamepad, GamepadButtonType::DPadLeft, 1.0);
+        press(&mut app, gamepad, GamepadButtonType::South, 1.0);
+        assert_eq!(app.world.resource::<Read>().inputs, Inputs::LEFT | Inputs::ROTATE_CCW);
+
+        press(&mut app, gamepad, GamepadButtonType::DPadLeft, 0.0);
+        assert_eq!(app.world.resource::<Read>().inputs, Inputs::ROTATE_CCW);
+    }
+
+    #[test]
+    fn left_stick_moves_past_the_deadzone() {
+        let (mut app, gamepad, _) = app();
+        let mut axes = app.world.resource_mut::<Axis<GamepadAxis>>();
+        axes.set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), 0.3);
+        axes.set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY), -0.9);
+        app.update();
+        assert_eq!(app.world.resource::<Read>().inputs, Inputs::SOFT_DROP);
+    }
+
+    #[test]
+    fn only_the_players_gamepad_pauses() {
+        let (mut app, gamepad, unassigned) = app();
+        press(&mut app, unassigned, GamepadButtonType::Start, 1.0);
+        let read = app.world.resource::<Read>();
+        assert!(!read.pause && !read.confirm);
+        assert_eq!(read.inputs, Inputs::NONE);
+
+        press(&mut app, gamepad, GamepadButtonType::Start, 1.0);
+        let read = app.world.resource::<Read>();
+        assert!(read.pause && read.confirm);
+    }
+}
@@ -1,13 +1,21 @@
 mod plugin;
 mod render;
 mod components;
+mod controls;
+mod replay;
 
+use std::process::ExitCode;
 use bevy::app::App;
 use bevy::DefaultPlugins;
 
-fn main() {
+fn main() -> ExitCode {
+    if let Some(code) = replay::run_headless(&std::env::args().collect::<Vec<_>>()) {
+        return code;
+    }
+
     App::new()
+        .insert_resource(components::Settings::from_args())
         .add_plugins((DefaultPlugins, plugin::Plugin))
         .run();
+    ExitCode::SUCCESS
 }
-
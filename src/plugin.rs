@@ -1,19 +1,26 @@
 use std::process::exit;
 use async_std::task;
-use crate::components::{BufferUpdate, Glitch, Locked, Position, RenderMarker, Score, Tetr, TetrisGame, Tetromino, TetroQueue, Updated};
-use crate::render::{render, render_events, Renderer};
+use crate::components::{BufferUpdate, GameOver, GameState, Glitch, Hold, LevelUp, LinesCleared, PieceLocked, PieceMoved, PieceRotated, PieceSpawned, Playback, Rebinding, Recording, RenderMarker, Restart, ResumeCountdown, Scored, Settings, Tetris, Viewer};
+use crate::controls::{Action, ActionInput, Controls, Players};
+use crate::replay;
+use crate::render::{glitch_on_clear, render, render_events, Renderer};
 use bevy::app::{App, MainScheduleOrder, PostUpdate, Startup};
 use bevy::ecs::schedule::{ExecutorKind, ScheduleLabel};
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
 use bevy::input::keyboard::KeyboardInput;
+use bevy::input::InputSystem;
 use bevy::prelude::*;
 use bevy::tasks::block_on;
 use bevy::time::TimerMode;
+use bevy::window::WindowFocused;
 use bevy::winit::{winit_runner, WinitWindows};
 use bevy_async_task::AsyncTask;
 use bevy_turborand::{DelegatedRng, GlobalRng};
 use bevy_turborand::prelude::{RngPlugin};
 use extend_lifetime::{extend_lifetime, ExtendableLife};
 use log::log;
+use tetris_core::{Game, GameEvent, Replay};
 use wasm_bindgen_futures::spawn_local;
 use winit::window::Window;
 
@@ -33,11 +40,37 @@ impl bevy::app::Plugin for Plugin {
             .add_plugins(RngPlugin::default())
             .add_systems(Startup, setup)
             .add_systems(Startup, setup_rendering)
-            .add_systems(Update, move_piece)
-            .add_systems(PostUpdate, update_board)
-            .add_systems(PostUpdate, spawn_new_piece)
-            .add_systems(Last, lock_pieces)
-            .insert_resource(TetrisGame::default())
+            .add_systems(Update, (game_state_input, restart_game).chain())
+            .add_systems(Update, open_rebinding.after(game_state_input))
+            .add_systems(Update, rebind_controls.run_if(in_state(GameState::Rebinding)))
+            .add_systems(PreUpdate, connect_gamepads.after(InputSystem))
+            .add_systems(Update, resume_countdown.after(game_state_input).run_if(in_state(GameState::Paused)))
+            .add_systems(FixedUpdate, step_game.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                viewer_input
+                    .after(restart_game)
+                    .run_if(not(in_state(GameState::Paused)).and_then(not(in_state(GameState::Rebinding)))),
+            )
+            .add_systems(Update, glitch_on_clear)
+            .add_systems(Update, save_recording)
+            .init_state::<GameState>()
+            .add_event::<PieceSpawned>()
+            .add_event::<PieceMoved>()
+            .add_event::<PieceRotated>()
+            .add_event::<PieceLocked>()
+            .add_event::<LinesCleared>()
+            .add_event::<LevelUp>()
+            .add_event::<Hold>()
+            .add_event::<GameOver>()
+            .add_event::<Scored>()
+            .add_event::<Restart>()
+            .init_resource::<Settings>()
+            .init_resource::<ResumeCountdown>()
+            .init_resource::<Recording>()
+            .init_resource::<Playback>()
+            .init_resource::<Rebinding>()
+            .init_resource::<Players>()
             .insert_resource(BufferUpdate(false))
             .set_runner(winit_runner);
 
@@ -46,15 +79,49 @@ impl bevy::app::Plugin for Plugin {
     }
 }
 
-fn setup(mut commands: Commands, rand: ResMut<GlobalRng>) {
-    let mut queue = TetroQueue::default();
-    queue.fill_queue(rand.into_inner());
-    commands.insert_resource(queue);
-    commands.insert_resource(MovePieceTimer(Timer::from_seconds(
-        1.0,
-        TimerMode::Repeating,
-    )));
+fn setup(mut commands: Commands, rand: ResMut<GlobalRng>, settings: Res<Settings>) {
+    let playback = settings.replay.as_deref().and_then(|path| {
+        replay::load(path).map(Viewer::new).map_err(|e| error!("{e}")).ok()
+    });
+    // Replays come with their own rules, including the tick rate
+    let tick_rate = playback.as_ref().map_or(settings.game.tick_rate, |viewer| viewer.timeline.replay().config.tick_rate);
+    let game = new_game(&settings, &playback, rand.into_inner());
+
+    // The game only advances in whole ticks, so the same inputs always lead to the same game
+    commands.insert_resource(Time::<Fixed>::from_hz(tick_rate as f64));
+    commands.insert_resource(Recording(start_recording(&settings, &playback, &game)));
+    commands.insert_resource(Playback(playback));
+    commands.insert_resource(Tetris(game));
     commands.insert_resource(Glitch::default());
+    commands.insert_resource(load_controls(&settings.controls));
+}
+
+/// Keys from the controls file, or the default ones if there is none yet
+fn load_controls(path: &str) -> Controls {
+    if !std::path::Path::new(path).exists() {
+        return Controls::default();
+    }
+    Controls::load(path).unwrap_or_else(|e| {
+        error!("{e}");
+        Controls::default()
+    })
+}
+
+/// Creates a new game, seeded from the settings if a seed is given, or the start of the replay being watched
+fn new_game(settings: &Settings, playback: &Option<Viewer>, rand: &mut GlobalRng) -> Game {
+    if let Some(viewer) = playback {
+        return viewer.timeline.seek(0);
+    }
+    let seed = settings.seed.unwrap_or_else(|| rand.u64(..));
+    Game::new(settings.game.clone(), seed)
+}
+
+/// Watched replays aren't recorded again
+fn start_recording(settings: &Settings, playback: &Option<Viewer>, game: &Game) -> Option<Replay> {
+    match (&settings.record, playback) {
+        (Some(_), None) => Some(Replay::record(game)),
+        _ => None,
+    }
 }
 
 fn setup_rendering(world: &mut World) {
@@ -78,153 +145,369 @@ fn setup_rendering(world: &mut World) {
     // let mut renderer = world.get_non_send_resource_mut::<Renderer>().unwrap();
     // renderer.into_inner().resize(window.inner_size().to_logical(1.0), window.inner_size());
     world.insert_non_send_resource(renderer);
-    world.insert_resource(Score::default());
     world.insert_resource(RenderMarker);
 
     info!("Rendering is set up!");
 }
 
-#[derive(Resource)]
-struct MovePieceTimer(Timer);
-
-fn move_piece(
-    mut query: Query<(&mut Tetr, &mut Updated), Without<Locked>>,
-    game: ResMut<TetrisGame>,
-    time: Res<Time>,
-    mut timer: ResMut<MovePieceTimer>,
-    input: Res<ButtonInput<KeyCode>>,
+fn game_state_input(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut restart: EventWriter<Restart>,
+    mut focus: EventReader<WindowFocused>,
+    mut countdown: ResMut<ResumeCountdown>,
+    settings: Res<Settings>,
+    actions: ActionInput,
 ) {
-    timer.0.tick(time.delta());
+    // There is a single game, played by the first player
+    let pause = actions.just_pressed(0, Action::Pause);
+    let focus_lost = focus.read().any(|e| !e.focused);
 
-    if timer.0.finished() {
-        for (mut tetr, mut updated) in query.iter_mut() {
-            if !updated.0 {
-                tetr.positions.iter_mut().for_each(|p| p.y -= 1);
-                updated.0 = true;
+    match state.get() {
+        GameState::Playing if pause || focus_lost => {
+            next_state.set(GameState::Paused);
+            return;
+        }
+        GameState::Paused if focus_lost => countdown.0 = None,
+        GameState::Paused if pause && countdown.0.is_none() => {
+            if settings.resume_countdown {
+                countdown.0 = Some(Timer::from_seconds(3.0, TimerMode::Once));
+            } else {
+                next_state.set(GameState::Playing);
             }
         }
+        _ => {}
     }
 
-    // FIXME: Fix moving into other pieces sideways
-    if input.just_pressed(KeyCode::ArrowLeft) {
-        for (mut tetr, mut updated) in query.iter_mut() {
-            let pos = tetr.positions.clone();
-            tetr.positions.iter_mut().for_each(|p| p.x -= 1);
-            if tetr.positions.iter().any(|p| p.x < 0) {
-                tetr.positions = pos;
-            }
-            updated.0 = true;
-        }
+    let start = match state.get() {
+        GameState::Menu | GameState::GameOver => actions.confirm(0) || actions.just_pressed(0, Action::Restart),
+        GameState::Playing => actions.just_pressed(0, Action::Restart),
+        GameState::Paused | GameState::Rebinding => false,
+    };
+
+    if start {
+        restart.send(Restart);
+        next_state.set(GameState::Playing);
     }
+}
 
-    if input.just_pressed(KeyCode::ArrowRight) {
-        for (mut tetr, mut updated) in query.iter_mut() {
-            let pos = tetr.positions.clone();
-            tetr.positions.iter_mut().for_each(|p| p.x += 1);
-            if tetr.positions.iter().any(|p| p.x > 9) {
-                tetr.positions = pos;
-            }
-            updated.0 = true;
-        }
+/// F1 opens the controls screen from the menus
+fn open_rebinding(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut rebinding: ResMut<Rebinding>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    let from = *state.get();
+    if matches!(from, GameState::Menu | GameState::Paused | GameState::GameOver) && input.just_pressed(KeyCode::F1) {
+        *rebinding = Rebinding { back_to: from, ..default() };
+        next_state.set(GameState::Rebinding);
     }
+}
 
-    if input.just_pressed(KeyCode::ArrowDown) {
-        for (mut tetr, mut updated) in query.iter_mut() {
-            tetr.positions.iter_mut().for_each(|p| p.y -= 1);
-            updated.0 = true;
+/// Controls screen: Up and Down pick an action, Enter waits for a key or gamepad button to add to it,
+/// Backspace removes all of its keys and buttons and Escape writes the controls back to their file.
+fn rebind_controls(
+    mut rebinding: ResMut<Rebinding>,
+    mut controls: ResMut<Controls>,
+    mut next_state: ResMut<NextState<GameState>>,
+    settings: Res<Settings>,
+    input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+) {
+    let action = Action::ALL[rebinding.selected];
+    if rebinding.waiting {
+        // Escape cancels instead of being bound, so the screen can always be left
+        if let Some(key) = input.get_just_pressed().next() {
+            if *key != KeyCode::Escape {
+                controls.bind(action, *key);
+            }
+            rebinding.waiting = false;
+        } else if let Some(button) = gamepad_input.get_just_pressed().next() {
+            controls.bind_button(action, button.button_type);
+            rebinding.waiting = false;
         }
+        return;
     }
 
+    let count = Action::ALL.len();
     if input.just_pressed(KeyCode::ArrowUp) {
-        for (mut tetr, _) in query.iter_mut() {
-            tetr.spin();
+        rebinding.selected = (rebinding.selected + count - 1) % count;
+    }
+    if input.just_pressed(KeyCode::ArrowDown) {
+        rebinding.selected = (rebinding.selected + 1) % count;
+    }
+    if input.just_pressed(KeyCode::Enter) {
+        rebinding.waiting = true;
+    }
+    if input.just_pressed(KeyCode::Backspace) {
+        controls.clear(action);
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        match controls.save(&settings.controls) {
+            Ok(()) => info!("Controls saved to {}", settings.controls),
+            Err(e) => error!("{e}"),
         }
+        next_state.set(rebinding.back_to);
     }
+}
 
-    if input.just_pressed(KeyCode::Space) {
-        // Move piece all the way down until it hits something
-        for (mut tetr, mut updated) in query.iter_mut() {
-            while !check_field_under(&game, &tetr.positions) {
-                tetr.positions.iter_mut().for_each(|p| p.y -= 1);
+/// Hands connected gamepads to the players and takes them away again once disconnected,
+/// losing the gamepad of the first player pauses the game.
+fn connect_gamepads(
+    mut connections: EventReader<GamepadConnectionEvent>,
+    mut players: ResMut<Players>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in connections.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) => match players.connect(event.gamepad) {
+                Some(player) => info!("{} connected for player {}", info.name, player + 1),
+                None => info!("{} connected, but every player already has a gamepad", info.name),
+            },
+            GamepadConnection::Disconnected => {
+                let Some(player) = players.disconnect(event.gamepad) else {
+                    continue;
+                };
+                info!("Gamepad of player {} disconnected", player + 1);
+                if player == 0 && *state.get() == GameState::Playing {
+                    next_state.set(GameState::Paused);
+                }
             }
-            updated.0 = true;
         }
     }
 }
 
-fn spawn_new_piece(mut commands: Commands, query: Query<(&mut Tetr, &mut Updated, Entity), Without<Locked>>, mut rand: ResMut<GlobalRng>, mut queue: ResMut<TetroQueue>, game: Res<TetrisGame>) {
-    if query.is_empty() {
-        let tetromino = queue.pop().unwrap_or(Tetromino::O);
-        let tetr = Tetr::new(tetromino);
-        // check if the piece can be spawned
-        if tetr.positions.iter().any(|p| game.field[p.y as usize][p.x as usize]) {
-            //exit(0);
-            return;
+fn resume_countdown(
+    mut countdown: ResMut<ResumeCountdown>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if let Some(timer) = countdown.0.as_mut() {
+        if timer.tick(time.delta()).finished() {
+            countdown.0 = None;
+            next_state.set(GameState::Playing);
         }
-        commands.spawn(tetr).insert(Updated(true));
-        if queue.len() < 2 { queue.fill_queue(rand.into_inner()); }
     }
 }
 
-fn check_field_under(game: &TetrisGame, positions: &[Position]) -> bool {
-    positions.iter().any(|p| p.y == 0 || game.field[p.y as usize - 1usize][p.x as usize])
+/// Resets everything back to how it was at startup
+fn restart_game(
+    mut events: EventReader<Restart>,
+    mut game: ResMut<Tetris>,
+    mut buffer_update: ResMut<BufferUpdate>,
+    mut recording: ResMut<Recording>,
+    playback: Res<Playback>,
+    settings: Res<Settings>,
+    rand: ResMut<GlobalRng>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    // A game that was abandoned is still worth keeping, its replay just ends early
+    if game.ticks() > 0 && game.over().is_none() {
+        write_recording(&mut recording, &game, &settings);
+    }
+    game.0 = new_game(&settings, &playback.0, rand.into_inner());
+    recording.0 = start_recording(&settings, &playback.0, &game);
+    buffer_update.0 = true;
+}
+
+/// Writes the replay of the game that just ended
+fn save_recording(
+    mut game_over: EventReader<GameOver>,
+    mut recording: ResMut<Recording>,
+    game: Res<Tetris>,
+    settings: Res<Settings>,
+) {
+    if game_over.read().count() > 0 {
+        write_recording(&mut recording, &game, &settings);
+    }
 }
 
-fn update_board(mut game: ResMut<TetrisGame>, mut tetr: Query<&mut Tetr, With<Locked>>, mut buffer_update: ResMut<BufferUpdate>, mut move_timer: ResMut<MovePieceTimer>, mut glitch: ResMut<Glitch>) {
-    for position in tetr.iter().flat_map(|t| t.positions.iter()) {
-        game.field[position.y as usize][position.x as usize] = true;
+fn write_recording(recording: &mut Recording, game: &Game, settings: &Settings) {
+    let (Some(replay), Some(path)) = (recording.0.as_mut(), settings.record.as_deref()) else {
+        return;
+    };
+    replay.finish(game);
+    match replay::save(path, replay) {
+        Ok(()) => info!("Replay saved to {path}"),
+        Err(e) => error!("{e}"),
     }
+}
 
-    // remove full rows
-    // TODO: Shift the rows above down
-    let mut removed_rows = Vec::new();
-    let mut row = 0;
-    while row < game.field.len() {
-        if game.field[row].iter().all(|&b| b) {
-            game.field[row] = [false; 10];
-            for mut tetr in &mut tetr {
-                tetr.positions.retain(|p| p.y != row as u32 as i32);
+/// Writers for everything the game reports
+#[derive(SystemParam)]
+struct GameEvents<'w> {
+    spawned: EventWriter<'w, PieceSpawned>,
+    moved: EventWriter<'w, PieceMoved>,
+    rotated: EventWriter<'w, PieceRotated>,
+    locked: EventWriter<'w, PieceLocked>,
+    lines_cleared: EventWriter<'w, LinesCleared>,
+    level_up: EventWriter<'w, LevelUp>,
+    hold: EventWriter<'w, Hold>,
+    game_over: EventWriter<'w, GameOver>,
+    scored: EventWriter<'w, Scored>,
+}
+
+impl GameEvents<'_> {
+    fn send(&mut self, event: GameEvent) {
+        match event {
+            GameEvent::Spawned(tetromino) => {
+                self.spawned.send(PieceSpawned(tetromino));
+            }
+            GameEvent::Moved { x, y } => {
+                self.moved.send(PieceMoved { x, y });
+            }
+            GameEvent::Rotated { direction, kick } => {
+                self.rotated.send(PieceRotated { direction, kick });
+            }
+            GameEvent::Held(tetromino) => {
+                self.hold.send(Hold(tetromino));
             }
-            buffer_update.0 = true;
-            removed_rows.push(row);
+            GameEvent::Locked { tetromino, spin } => {
+                self.locked.send(PieceLocked { tetromino, spin });
+            }
+            GameEvent::Scored(scored) => {
+                self.scored.send(Scored(scored));
+            }
+            GameEvent::LinesCleared { rows, kind } => {
+                self.lines_cleared.send(LinesCleared { rows, kind });
+            }
+            GameEvent::LevelUp(level) => {
+                self.level_up.send(LevelUp(level));
+            }
+            GameEvent::GameOver(top_out) => {
+                self.game_over.send(GameOver(top_out));
+            }
+            GameEvent::RowsRemoved(_) => {}
         }
-        row += 1;
     }
+}
 
-    for (i, e) in removed_rows.iter_mut().enumerate() {
-        let e = *e - i;
-        tetr.for_each_mut(|mut tetr| {
-            tetr.positions.iter_mut().for_each(|p| {
-                if p.y > (e as i32) {
-                    p.y -= 1;
-                }
-            });
-        });
+/// Forwards what happened in a tick as events
+fn send_events(
+    happened: Vec<GameEvent>,
+    buffer_update: &mut BufferUpdate,
+    events: &mut GameEvents,
+    next_state: &mut NextState<GameState>,
+) {
+    for event in happened {
+        buffer_update.0 = true;
+        if let GameEvent::GameOver(_) = event {
+            next_state.set(GameState::GameOver);
+        }
+        events.send(event);
+    }
+}
+
+/// Advances the game by ticks, with the inputs from the keyboard and gamepad of the first player,
+/// or from the replay being watched at its speed.
+fn step_game(
+    mut game: ResMut<Tetris>,
+    actions: ActionInput,
+    mut recording: ResMut<Recording>,
+    mut playback: ResMut<Playback>,
+    mut buffer_update: ResMut<BufferUpdate>,
+    mut events: GameEvents,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(viewer) = playback.0.as_mut() else {
+        let inputs = actions.inputs(0);
+        if let Some(replay) = recording.0.as_mut() {
+            replay.push(inputs);
+        }
+        let happened = game.tick(inputs);
+        send_events(happened, &mut buffer_update, &mut events, &mut next_state);
+        return;
+    };
+
+    if viewer.paused {
+        return;
     }
+    viewer.pending += viewer.speed;
+    while viewer.pending >= 1.0 {
+        viewer.pending -= 1.0;
+        let Some(inputs) = viewer.timeline.inputs(game.ticks()) else {
+            // The replay was stopped before the game ended
+            viewer.pending = 0.0;
+            next_state.set(GameState::GameOver);
+            return;
+        };
+        let happened = game.tick(inputs);
+        send_events(happened, &mut buffer_update, &mut events, &mut next_state);
+    }
+}
+
+/// Controls for watching a replay: Space pauses, Up and Down change the speed,
+/// Left and Right step a single tick, Comma and Period jump to the previous or next piece,
+/// and typing a piece number followed by G jumps to that piece.
+fn viewer_input(
+    mut playback: ResMut<Playback>,
+    mut game: ResMut<Tetris>,
+    mut buffer_update: ResMut<BufferUpdate>,
+    mut next_state: ResMut<NextState<GameState>>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    let Some(viewer) = playback.0.as_mut() else {
+        return;
+    };
 
-    if !removed_rows.is_empty() {
-        game.field = [[false; 10]; 40];
-        glitch.0 = removed_rows.len() as f32;
+    if input.just_pressed(KeyCode::Space) {
+        viewer.paused = !viewer.paused;
+    }
+    let speed = Viewer::SPEEDS.iter().position(|&s| s == viewer.speed).unwrap_or(2);
+    if input.just_pressed(KeyCode::ArrowUp) {
+        viewer.speed = Viewer::SPEEDS[(speed + 1).min(Viewer::SPEEDS.len() - 1)];
+    }
+    if input.just_pressed(KeyCode::ArrowDown) {
+        viewer.speed = Viewer::SPEEDS[speed.saturating_sub(1)];
     }
 
-    //game.score.score += removed_rows.len() as u32;
-    if game.score.increase(removed_rows.len() as u32) {
-        move_timer.0 = game.score.timer();
+    let digits = [
+        KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+        KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    ];
+    for (digit, key) in digits.iter().enumerate() {
+        if input.just_pressed(*key) && viewer.piece.len() < 6 {
+            viewer.piece.push_str(&digit.to_string());
+        }
+    }
+    if input.just_pressed(KeyCode::Backspace) {
+        viewer.piece.pop();
     }
 
-    // We need to remove the locked drawables to make it work...
-    // And then there also is the buffer where we have written to sequentially,
-    // so we actually either need to shift inside the buffer or just
-    // simply overwrite it completely (performance vs simplicity)
-    // TODO: implement this (probably just overwrite the buffer each time... for now)
-    //       - Shouldn't be too performance heavy, as we only have to do this when a row is cleared
-}
+    let tick = game.ticks();
+    let current = viewer.timeline.piece_at(tick);
+    let target = if input.just_pressed(KeyCode::ArrowRight) {
+        Some(tick + 1)
+    } else if input.just_pressed(KeyCode::ArrowLeft) {
+        Some(tick.saturating_sub(1))
+    } else if input.just_pressed(KeyCode::Period) {
+        viewer.timeline.piece_tick(current + 1)
+    } else if input.just_pressed(KeyCode::Comma) {
+        // Back to the start of the current piece, or the one before if it just spawned
+        match viewer.timeline.piece_tick(current) {
+            Some(spawned) if spawned < tick => Some(spawned),
+            _ => viewer.timeline.piece_tick(current.saturating_sub(1)).or(Some(0)),
+        }
+    } else if input.just_pressed(KeyCode::KeyG) {
+        let piece = std::mem::take(&mut viewer.piece).parse().unwrap_or(0);
+        viewer.timeline.piece_tick(piece)
+    } else {
+        None
+    };
 
-// TODO: might need some work as the player might want to slide the piece to the left or right when touching the ground
-fn lock_pieces(mut commands: Commands, query: Query<(Entity, &Tetr), Without<Locked>>, game: Res<TetrisGame>) {
-    for (entity, tetr) in query.iter() {
-        if check_field_under(&game, &tetr.positions) {
-            commands.get_entity(entity).unwrap().insert(Locked);
+    if let Some(target) = target {
+        // Stepping through a replay only makes sense while it stands still
+        if input.any_just_pressed([KeyCode::ArrowLeft, KeyCode::ArrowRight]) {
+            viewer.paused = true;
         }
+        game.0 = viewer.timeline.seek(target);
+        viewer.pending = 0.0;
+        buffer_update.0 = true;
+        next_state.set(if game.over().is_some() { GameState::GameOver } else { GameState::Playing });
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
-use crate::components::{BufferUpdate, Drawable, Glitch, Locked, RenderMarker, Score, Tetr, TetrisGame, Tetromino, TetroQueue, Updated};
-use bevy::prelude::{Commands, EventReader, Has, Local, NonSendMut, Query, Res, ResMut, World};
+use crate::controls::Controls;
+use crate::components::{ghost_drawables, preview_drawables, AsDrawables, BufferUpdate, Drawable, GameState, Glitch, LinesCleared, Playback, Rebinding, RenderMarker, ResumeCountdown, Scored, Settings, Tetris};
+use bevy::prelude::{Commands, EventReader, Local, NonSendMut, Res, ResMut, State, World};
 use bevy::time::{Fixed, Time};
 use bevy::utils::default;
 use bevy::window::{RequestRedraw, WindowResized};
@@ -14,6 +15,7 @@ use winit::dpi::{LogicalSize, PhysicalSize};
 
 use winit::window::Window;
 use log::info;
+use tetris_core::{Phase, Score, Tetromino};
 
 const VERTICES: &[Vertex] = &[
     Vertex {
@@ -66,6 +68,15 @@ pub struct Renderer {
     //window: Box<&'static Window>,
     score: Score,
     next_tetro: Option<Tetromino>,
+    held_tetro: Option<Tetromino>,
+    state: GameState,
+    countdown: Option<u32>,
+    announcement: String,
+    announced_at: f32,
+    /// Status line of the replay being watched, empty while playing
+    replay: String,
+    /// Text of the controls screen
+    controls: String,
 }
 
 impl Renderer {
@@ -372,6 +383,13 @@ impl Renderer {
             texture_bind_group,
             score: Score::default(),
             next_tetro: None,
+            held_tetro: None,
+            state: GameState::default(),
+            countdown: None,
+            announcement: String::new(),
+            announced_at: 0.0,
+            replay: String::new(),
+            controls: String::new(),
         }
     }
 
@@ -428,7 +446,16 @@ impl Renderer {
         }
 
         self.text_buffer.set_size(&mut self.font_system, self.size.width as f32, self.size.height as f32);
-        self.text_buffer.set_text(&mut self.font_system, &*(format!("LINES - {}/{}\nLEVEL - {}\nNEXT TETRO - {}", self.score.score, self.score.goal(), self.score.level, self.next_tetro.unwrap_or(Tetromino::O))), Attrs::new().family(Family::Name("Digit Tech 14")).color(Color::rgb(255, 255, 255)), Shaping::Advanced);
+        let state = match (self.state, self.countdown) {
+            (GameState::Menu, _) => "\n\nPRESS ENTER TO START\nF1 - CONTROLS".to_string(),
+            (GameState::GameOver, _) => "\n\nGAME OVER\nPRESS ENTER TO RESTART\nF1 - CONTROLS".to_string(),
+            (GameState::Paused, Some(count)) => format!("\n\nRESUMING IN {}", count),
+            (GameState::Paused, None) => "\n\nPAUSED\nPRESS ESC TO RESUME\nF1 - CONTROLS".to_string(),
+            (GameState::Rebinding, _) => format!("\n\n{}", self.controls),
+            _ => String::new(),
+        };
+        let announcement = if self.uniforms.time - self.announced_at < 2.0 { self.announcement.as_str() } else { "" };
+        self.text_buffer.set_text(&mut self.font_system, &*(format!("SCORE - {}\nLINES - {}/{}\nLEVEL - {}\nNEXT TETRO - {}\nHOLD TETRO - {}{}\n\n{}\n\n{}", self.score.points, self.score.score, self.score.goal(), self.score.level, self.next_tetro.unwrap_or(Tetromino::O), self.held_tetro.map_or("-".to_string(), |t| t.to_string()), state, announcement, self.replay)), Attrs::new().family(Family::Name("Digit Tech 14")).color(Color::rgb(255, 255, 255)), Shaping::Advanced);
         self.text_buffer.shape_until_scroll(&mut self.font_system);
 
         // Apply Texture to surface_view
@@ -443,8 +470,8 @@ impl Renderer {
             bounds: TextBounds {
                 left: 0,
                 top: 0,
-                right: 600,
-                bottom: 360,
+                right: 800,
+                bottom: self.size.height as i32,
             },
             default_color: Color::rgb(255, 255, 255),
         }], &mut self.swash_cache).unwrap();
@@ -519,6 +546,9 @@ struct Uniforms {
     pub window_size: [f32; 2],
     pub scale: f32,
     pub window_scale: f32,
+    /// Columns and visible rows of the playfield
+    pub board_size: [f32; 2],
+    _padding: [f32; 2],
 }
 
 impl Default for Uniforms {
@@ -530,6 +560,8 @@ impl Default for Uniforms {
             window_size: [0.0, 0.0],
             scale: SCALE,
             window_scale: 1.0,
+            board_size: [10.0, 20.0],
+            _padding: [0.0; 2],
         }
     }
 }
@@ -544,9 +576,13 @@ unsafe impl bytemuck::Zeroable for Uniforms {}
 
 unsafe impl bytemuck::Pod for Uniforms {}
 
+/// Length of the drawables array in the shader, enough for every visible cell of the largest board
+/// `Settings::from_args` allows (16 × 40) together with the falling tetromino, its ghost and the preview
+const MAX_DRAWABLES: usize = 1024;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
-struct Drawables([Drawable; 256]);
+struct Drawables([Drawable; MAX_DRAWABLES]);
 
 impl Drawables {
     fn as_bytes(&self) -> &[u8] {
@@ -556,7 +592,7 @@ impl Drawables {
 
 impl Default for Drawables {
     fn default() -> Self {
-        Self([Default::default(); 256])
+        Self([Default::default(); MAX_DRAWABLES])
     }
 }
 
@@ -567,11 +603,15 @@ unsafe impl bytemuck::Pod for Drawables {}
 pub fn render(
     mut renderer: NonSendMut<Renderer>,
     _time: Res<Time<Fixed>>,
-    mut tetrs: Query<(&Tetr, &mut Updated, Has<Locked>)>,
     mut buffer_update: ResMut<BufferUpdate>,
     _commands: Commands,
-    game: Res<TetrisGame>,
-    queue: Res<TetroQueue>,
+    game: Res<Tetris>,
+    state: Res<State<GameState>>,
+    countdown: Res<ResumeCountdown>,
+    settings: Res<Settings>,
+    playback: Res<Playback>,
+    (rebinding, controls): (Res<Rebinding>, Res<Controls>),
+    mut scored: EventReader<Scored>,
     instant: Res<Time<Fixed>>,
     mut frame_count: Local<u32>,
     mut last_time: Local<f32>,
@@ -589,46 +629,71 @@ pub fn render(
     //  This is just to get it working not - performance isn't a concern right now
     //  Aaaaand I don't ever want to deal with memory fragmentation...
 
-    let vec = if buffer_update.0 {
-        tetrs
-            .iter()
-            .map(|e| e.0)
-            .collect::<Vec<&Tetr>>()
-    } else {
-        tetrs
-            .iter()
-            .filter(|e| e.1.0)
-            .map(|e| e.0)
-            .collect::<Vec<&Tetr>>()
-    };
-
+    let board = game.board();
+
+    // The field is cleared while paused, so pausing can't be used to study the stack
+    let hidden = matches!(state.get(), GameState::Paused | GameState::Rebinding);
+
+    let active = game.active().map(|t| t.as_drawables()).unwrap_or_default();
+
+    let ghost = game
+        .active()
+        .filter(|_| settings.ghost)
+        .map(|t| ghost_drawables(t, board))
+        .unwrap_or_default();
+
+    // Miniatures of the upcoming tetrominoes to the right of the field
+    let preview = (0..game.config().preview)
+        .filter_map(|i| game.queue().get(i).map(|t| preview_drawables(*t, board.width as f32 + 1.0, board.visible_height as f32 - 2.0 - i as f32 * 2.5, 0.6)))
+        .flatten()
+        .collect::<Vec<Drawable>>();
+
+    // Clearing rows flash white and shrink until the line clear delay is over
+    let mut field = board.as_drawables();
+    if let Phase::LineClear { rows, delay } = game.phase() {
+        let progress = delay.fraction();
+        for d in field.iter_mut().filter(|d| rows.contains(&(d.position[1] as usize))) {
+            for size in &mut d.shape_data[0..3] {
+                *size *= 1.0 - progress;
+            }
+            for color in &mut d.shape_data[4..7] {
+                *color += (1.0 - *color) * (1.0 - progress);
+            }
+        }
+    }
 
-    let e = vec
-        .iter()
-        .flat_map(|e| e.as_drawables())
-        .filter(|e| e.shape_data[7] != 0.0)
+    // The pieces go first, so if a stack in the buffer rows doesn't fit anymore only field cells are left out
+    let e = active
+        .into_iter()
+        .chain(ghost)
+        .chain(preview)
+        .chain(field)
+        .filter(|e| e.shape_data[7] != 0.0 && !hidden)
+        .take(MAX_DRAWABLES)
         .flat_map(|d| d.as_bytes().to_vec())
         .collect::<Vec<u8>>();
 
-    if !e.is_empty() {
-        if buffer_update.0 {
-            // fill e with 0s until size of buffer is reached to overwrite old data
-            let mut e = e;
-            e.resize(renderer.drawables_buffer.size() as usize, 0);
-            renderer
-                .queue
-                .write_buffer(&renderer.drawables_buffer, 0, e.as_slice());
-        } else {
-            let offset = tetrs.iter().filter(|e| e.2).map(|e| e.0.offset()).sum::<u64>();
-
-            renderer
-                .queue
-                .write_buffer(&renderer.drawables_buffer, offset, e.as_slice());
-        }
+    if (!e.is_empty() || hidden) && buffer_update.0 {
+        // fill e with 0s until size of buffer is reached to overwrite old data
+        let mut e = e;
+        e.resize(renderer.drawables_buffer.size() as usize, 0);
+        renderer
+            .queue
+            .write_buffer(&renderer.drawables_buffer, 0, e.as_slice());
     }
 
-    renderer.score = game.score;
-    renderer.next_tetro = queue.get(0).copied();
+    renderer.uniforms.board_size = [board.width as f32, board.visible_height as f32];
+    renderer.score = *game.score();
+    renderer.next_tetro = game.queue().get(0).copied();
+    renderer.held_tetro = board.hold;
+    renderer.state = *state.get();
+    if let Some(event) = scored.read().last() {
+        renderer.announcement = event.0.to_string();
+        renderer.announced_at = renderer.uniforms.time;
+    }
+    renderer.countdown = countdown.0.as_ref().map(|t| t.remaining_secs().ceil() as u32);
+    renderer.controls = rebinding.screen(&controls);
+    renderer.replay = playback.0.as_ref().map(|viewer| viewer.status(&game)).unwrap_or_default();
     renderer
         .queue
         .write_buffer(&renderer.uniforms_buffer, 0, renderer.uniforms.as_bytes());
@@ -641,12 +706,16 @@ pub fn render(
     //    thread::sleep(frame_time - elapsed_time);
     //}
 
-    for mut e in tetrs.iter_mut() {
-        e.1.0 = false;
-    }
     buffer_update.0 = false;
 }
 
+/// Glitches the screen harder the more lines get cleared at once
+pub fn glitch_on_clear(mut lines_cleared: EventReader<LinesCleared>, mut glitch: ResMut<Glitch>) {
+    for event in lines_cleared.read() {
+        glitch.0 = event.rows.len() as f32;
+    }
+}
+
 pub fn render_events(
     mut renderer: NonSendMut<Renderer>,
     mut redraw: EventReader<RequestRedraw>,
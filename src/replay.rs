@@ -0,0 +1,44 @@
+use std::process::ExitCode;
+use tetris_core::Replay;
+
+pub fn load(path: &str) -> Result<Replay, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    Replay::from_bytes(&bytes).map_err(|e| format!("can't load {path}: {e}"))
+}
+
+pub fn save(path: &str, replay: &Replay) -> Result<(), String> {
+    std::fs::write(path, replay.to_bytes()).map_err(|e| format!("can't write {path}: {e}"))
+}
+
+/// Handles `--verify-replay <file>` and `--dump-replay <file>` without opening a window,
+/// returns `None` if neither is given and the game should start.
+pub fn run_headless(args: &[String]) -> Option<ExitCode> {
+    for pair in args.windows(2) {
+        let result = match pair[0].as_str() {
+            "--verify-replay" => verify(&pair[1]),
+            "--dump-replay" => load(&pair[1]).map(|replay| print!("{}", replay.to_json())),
+            _ => continue,
+        };
+        return Some(match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        });
+    }
+    None
+}
+
+fn verify(path: &str) -> Result<(), String> {
+    let replay = load(path)?;
+    let result = replay.verify().map_err(|e| format!("{path}: {e}"))?;
+    println!(
+        "{path}: {} ticks reproduced, {} points, {} lines, level {}",
+        replay.inputs.len(),
+        result.points,
+        result.lines,
+        result.level
+    );
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use crate::piece::{Position, Tetr, Tetromino};
+use crate::scoring::Score;
+
+/// Content of a single cell of the playfield
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Cell {
+    #[default]
+    Empty,
+    /// Block of a locked tetromino, keeps its color
+    Piece(Tetromino),
+    /// Block of a garbage row
+    Garbage,
+    /// Block with an arbitrary RGB color, e.g. from an imported board
+    Custom([f32; 3]),
+}
+
+impl Cell {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Cell::Empty)
+    }
+
+    /// RGB format, `None` for empty cells
+    pub fn color(&self) -> Option<[f32; 3]> {
+        match self {
+            Cell::Empty => None,
+            Cell::Piece(tetromino) => Some(tetromino.color()),
+            Cell::Garbage => Some([0.5, 0.5, 0.5]),
+            Cell::Custom(color) => Some(*color),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TetrisGame {
+    /// Playfield is 10×40, where rows above 20 are hidden or obstructed by the field frame to trick the player into thinking it's 10×20.
+    /// | Guidelines
+    ///
+    /// Locked tetrominoes are merged into the field, only the falling one is kept separately.
+    /// Indexed as `field[y][x]` starting at the bottom left, the size is configurable for variants.
+    pub field: Vec<Vec<Cell>>,
+    pub width: usize,
+    /// Rows above this one are hidden behind the field frame
+    pub visible_height: usize,
+    pub hold: Option<Tetromino>,
+    /// Only one hold per piece is allowed, reset once the next piece spawns
+    pub can_hold: bool,
+    pub score: Score,
+}
+
+impl TetrisGame {
+    /// Empty playfield with `buffer_height` hidden rows on top of the visible ones
+    pub fn new(width: usize, visible_height: usize, buffer_height: usize) -> Self {
+        TetrisGame {
+            field: vec![vec![Cell::Empty; width]; visible_height + buffer_height],
+            width,
+            visible_height,
+            hold: None,
+            can_hold: true,
+            score: Score::default(),
+        }
+    }
+
+    /// Visible and hidden rows together
+    pub fn height(&self) -> usize {
+        self.field.len()
+    }
+
+    /// Indices of all rows without any empty cell, from bottom to top
+    pub fn full_rows(&self) -> Vec<usize> {
+        (0..self.field.len())
+            .filter(|&row| self.field[row].iter().all(|c| !c.is_empty()))
+            .collect()
+    }
+
+    /// Removes the given rows, everything above them moves down and empty rows are added at the top
+    pub fn clear_rows(&mut self, rows: &[usize]) {
+        let width = self.width;
+        self.field = self
+            .field
+            .drain(..)
+            .enumerate()
+            .filter(|(row, _)| !rows.contains(row))
+            .map(|(_, cells)| cells)
+            .collect();
+        self.field.resize(self.field.len() + rows.len(), vec![Cell::Empty; width]);
+    }
+
+    /// Writes the blocks of a locked tetromino into the field
+    pub fn merge(&mut self, tetr: &Tetr) {
+        for position in &tetr.positions {
+            self.field[position.y as usize][position.x as usize] = Cell::Piece(tetr.tetromino);
+        }
+    }
+
+    /// Whether there is no block left in the field once the given rows are removed
+    pub fn is_empty_without(&self, rows: &[usize]) -> bool {
+        self.field
+            .iter()
+            .enumerate()
+            .filter(|(row, _)| !rows.contains(row))
+            .all(|(_, cells)| cells.iter().all(|c| c.is_empty()))
+    }
+
+    /// FNV-1a hash over all cells, used to compare the final board of a replay
+    pub fn checksum(&self) -> u64 {
+        let mut hash = 0xcbf29ce484222325u64;
+        let mut feed = |byte: u8| hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        for cell in self.field.iter().flatten() {
+            match cell {
+                Cell::Empty => feed(0),
+                Cell::Piece(tetromino) => feed(1 + *tetromino as u8),
+                Cell::Garbage => feed(8),
+                Cell::Custom(color) => {
+                    feed(9);
+                    color.iter().flat_map(|c| c.to_le_bytes()).for_each(&mut feed);
+                }
+            }
+        }
+        hash
+    }
+
+    /// Checks whether all positions are inside the playfield and not occupied by a locked block
+    pub fn fits(&self, positions: &[Position]) -> bool {
+        positions.iter().all(|p| {
+            p.x >= 0 && p.x < self.width as i32 && p.y >= 0 && p.y < self.height() as i32 && self.field[p.y as usize][p.x as usize].is_empty()
+        })
+    }
+}
+
+impl Default for TetrisGame {
+    fn default() -> Self {
+        TetrisGame::new(10, 20, 20)
+    }
+}
+
+#[cfg(test)]
+impl TetrisGame {
+    /// Standard 10×40 playfield with the given rows at the bottom, written top to bottom.
+    /// `.` is empty, `#` garbage and a letter the block of that tetromino.
+    pub(crate) fn from_rows(rows: &[&str]) -> Self {
+        let mut game = TetrisGame::default();
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                game.field[y][x] = match c {
+                    '.' => Cell::Empty,
+                    '#' => Cell::Garbage,
+                    'I' => Cell::Piece(Tetromino::I),
+                    'O' => Cell::Piece(Tetromino::O),
+                    'T' => Cell::Piece(Tetromino::T),
+                    'S' => Cell::Piece(Tetromino::S),
+                    'Z' => Cell::Piece(Tetromino::Z),
+                    'J' => Cell::Piece(Tetromino::J),
+                    'L' => Cell::Piece(Tetromino::L),
+                    _ => panic!("unknown cell {c}"),
+                };
+            }
+        }
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(game: &TetrisGame, y: usize) -> String {
+        game.field[y]
+            .iter()
+            .map(|c| match c {
+                Cell::Empty => '.',
+                Cell::Piece(t) => t.to_string().chars().next().unwrap(),
+                _ => '#',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clears_a_single_row() {
+        let mut game = TetrisGame::from_rows(&[
+            "..T.......",
+            "IIIIJJJLLL",
+            "#.########",
+        ]);
+        assert_eq!(game.full_rows(), vec![1]);
+
+        game.clear_rows(&[1]);
+        assert_eq!(row(&game, 0), "#.########");
+        assert_eq!(row(&game, 1), "..T.......");
+        assert_eq!(game.field[1][2], Cell::Piece(Tetromino::T));
+        assert_eq!(row(&game, 2), "..........");
+        assert_eq!(game.height(), 40);
+    }
+
+    #[test]
+    fn clears_rows_that_are_not_next_to_each_other() {
+        let mut game = TetrisGame::from_rows(&[
+            "S.........",
+            "ZZZZZZZZZZ",
+            ".J........",
+            "OOOOOOOOOO",
+            "..L.......",
+        ]);
+        assert_eq!(game.full_rows(), vec![1, 3]);
+
+        game.clear_rows(&[1, 3]);
+        assert_eq!(row(&game, 0), "..L.......");
+        assert_eq!(row(&game, 1), ".J........");
+        assert_eq!(row(&game, 2), "S.........");
+        assert_eq!(game.field[1][1], Cell::Piece(Tetromino::J));
+        assert_eq!(game.field[2][0], Cell::Piece(Tetromino::S));
+        assert!(game.field[3..].iter().flatten().all(Cell::is_empty));
+    }
+
+    #[test]
+    fn clears_a_tetris() {
+        let mut game = TetrisGame::from_rows(&[
+            "ZZ........",
+            "#########I",
+            "#########I",
+            "#########I",
+            "#########I",
+            "#.#.#.#.#.",
+        ]);
+        assert_eq!(game.full_rows(), vec![1, 2, 3, 4]);
+
+        game.clear_rows(&[1, 2, 3, 4]);
+        assert_eq!(row(&game, 0), "#.#.#.#.#.");
+        assert_eq!(row(&game, 1), "ZZ........");
+        assert_eq!(game.field[1][0], Cell::Piece(Tetromino::Z));
+        assert!(game.field[2..].iter().flatten().all(Cell::is_empty));
+    }
+
+    #[test]
+    fn clears_the_top_buffer_row() {
+        let mut game = TetrisGame::default();
+        let top = game.height() - 1;
+        game.field[top] = vec![Cell::Piece(Tetromino::I); 10];
+        game.field[top - 1][4] = Cell::Piece(Tetromino::L);
+        assert_eq!(game.full_rows(), vec![top]);
+
+        game.clear_rows(&[top]);
+        assert_eq!(game.height(), 40);
+        assert_eq!(row(&game, top), "..........");
+        assert_eq!(game.field[top - 1][4], Cell::Piece(Tetromino::L));
+    }
+}
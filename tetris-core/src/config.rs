@@ -0,0 +1,174 @@
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use crate::randomizer::RandomizerKind;
+use crate::scoring::ScoringSystem;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum GameMode {
+    #[default]
+    Marathon,
+    VariableMarathon,
+    Nes,
+    Classic,
+}
+
+impl GameMode {
+    pub fn scoring(&self) -> ScoringSystem {
+        match self {
+            GameMode::Marathon => ScoringSystem::FixedGoal,
+            GameMode::VariableMarathon => ScoringSystem::VariableGoal,
+            GameMode::Nes => ScoringSystem::Nes,
+            GameMode::Classic => ScoringSystem::Classic,
+        }
+    }
+
+    /// Default entry delay in seconds, NES waits 10 to 18 frames depending on the lock height,
+    /// we use the shortest one
+    pub fn are(&self) -> f32 {
+        match self {
+            GameMode::Nes => 10.0 / 60.0988,
+            _ => 0.0,
+        }
+    }
+
+    /// Default line clear delay in seconds, roughly the 17 to 20 frames NES takes for its animation
+    pub fn line_clear_delay(&self) -> f32 {
+        match self {
+            GameMode::Nes => 18.0 / 60.0988,
+            _ => 0.0,
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "marathon" => Ok(GameMode::Marathon),
+            "variable" | "variable-marathon" => Ok(GameMode::VariableMarathon),
+            "nes" => Ok(GameMode::Nes),
+            "classic" => Ok(GameMode::Classic),
+            _ => Err(format!("unknown game mode: {s}")),
+        }
+    }
+}
+
+/// Rules of a game, everything that changes how it plays
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameConfig {
+    pub mode: GameMode,
+    /// 0 to 29
+    pub start_level: u32,
+    pub lock_down: LockDownMode,
+    /// Delayed Auto Shift, seconds a direction has to be held before it starts repeating
+    pub das: f32,
+    /// Auto Repeat Rate, seconds between repeated shifts, 0 moves the piece to the wall instantly
+    pub arr: f32,
+    /// DAS cut, seconds the auto repeat is paused after a rotation
+    pub das_cut: f32,
+    /// Soft drop factor, multiplies the gravity while soft dropping, infinity drops instantly
+    pub soft_drop_factor: f32,
+    /// Fixed gravity in G instead of the one of the scoring system, 20 drops pieces instantly
+    pub gravity: Option<f32>,
+    /// Number of upcoming tetrominoes that are known in advance, 1 to 6
+    pub preview: usize,
+    pub randomizer: RandomizerKind,
+    /// Columns of the playfield, 4 to 16
+    pub width: usize,
+    /// Rows of the playfield that are shown, 4 to 40
+    pub visible_height: usize,
+    /// Hidden rows above the visible ones, pieces spawn in there and may be rotated or lock into it, 4 to 40
+    pub buffer_height: usize,
+    /// Entry delay (ARE), seconds between a lock (or the end of a line clear) and the next spawn,
+    /// the default of the game mode is used if not set
+    pub are: Option<f32>,
+    /// Seconds full rows stay on the field before they are removed, the default of the game mode is used if not set
+    pub line_clear_delay: Option<f32>,
+    /// Initial Rotation System, a rotation pressed during the entry delay and held while the next tetromino spawns rotates it right away
+    pub irs: bool,
+    /// Initial Hold System, hold pressed during the entry delay and held while the next tetromino spawns holds it right away
+    pub ihs: bool,
+    /// Ticks per second, all timings above are rounded to whole ticks
+    pub tick_rate: u32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            mode: GameMode::default(),
+            start_level: 0,
+            lock_down: LockDownMode::default(),
+            das: 0.167,
+            arr: 0.033,
+            das_cut: 0.0,
+            soft_drop_factor: 20.0,
+            gravity: None,
+            preview: 5,
+            randomizer: RandomizerKind::default(),
+            width: 10,
+            visible_height: 20,
+            buffer_height: 20,
+            are: None,
+            line_clear_delay: None,
+            irs: true,
+            ihs: true,
+            tick_rate: 60,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Levels a game can start at, the NES gravity doesn't change anymore after 29
+    pub const START_LEVELS: RangeInclusive<u32> = 0..=29;
+    pub const PREVIEWS: RangeInclusive<usize> = 1..=6;
+    pub const WIDTHS: RangeInclusive<usize> = 4..=16;
+    /// Allowed visible and buffer heights alike
+    pub const HEIGHTS: RangeInclusive<usize> = 4..=40;
+    pub const TICK_RATES: RangeInclusive<u32> = 1..=1000;
+
+    /// Seconds rounded to the nearest number of ticks
+    pub fn ticks(&self, seconds: f32) -> u32 {
+        (seconds * self.tick_rate as f32).round() as u32
+    }
+
+    /// Seconds a single tick takes
+    pub fn tick_duration(&self) -> f32 {
+        1.0 / self.tick_rate as f32
+    }
+
+    pub fn are(&self) -> f32 {
+        self.are.unwrap_or(self.mode.are())
+    }
+
+    pub fn line_clear_delay(&self) -> f32 {
+        self.line_clear_delay.unwrap_or(self.mode.line_clear_delay())
+    }
+}
+
+/// Lock Down
+/// Extended Placement: the 0.5 second lock down timer resets on every move or rotation,
+/// but only 15 times until the tetromino falls below the lowest row it has reached.
+/// Infinite Placement: same as Extended Placement, but without a limit.
+/// Classic: the timer only resets once the tetromino falls a row.
+/// | Guidelines
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LockDownMode {
+    #[default]
+    Extended,
+    Infinite,
+    StepReset,
+}
+
+impl FromStr for LockDownMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "extended" => Ok(LockDownMode::Extended),
+            "infinite" => Ok(LockDownMode::Infinite),
+            "step" | "step-reset" | "classic" => Ok(LockDownMode::StepReset),
+            _ => Err(format!("unknown lock down mode: {s}")),
+        }
+    }
+}
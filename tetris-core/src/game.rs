@@ -0,0 +1,952 @@
+use std::ops::BitOr;
+use crate::board::TetrisGame;
+use crate::config::{GameConfig, LockDownMode};
+use crate::piece::{Position, RotationDirection, Spin, Tetr, Tetromino};
+use crate::randomizer::TetroQueue;
+use crate::scoring::{ClearKind, Score, Scored};
+
+/// Top Out
+/// Block Out: a newly spawned tetromino overlaps a block of the stack.
+/// Lock Out: a tetromino locks down completely above the visible playfield.
+/// | Guidelines
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TopOut {
+    BlockOut,
+    LockOut,
+}
+
+/// Buttons held during a step, the game keeps the previous ones to tell presses from holds
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Inputs(pub u16);
+
+impl Inputs {
+    pub const NONE: Inputs = Inputs(0);
+    pub const LEFT: Inputs = Inputs(1 << 0);
+    pub const RIGHT: Inputs = Inputs(1 << 1);
+    pub const SOFT_DROP: Inputs = Inputs(1 << 2);
+    pub const HARD_DROP: Inputs = Inputs(1 << 3);
+    pub const ROTATE_CW: Inputs = Inputs(1 << 4);
+    pub const ROTATE_CCW: Inputs = Inputs(1 << 5);
+    pub const ROTATE_180: Inputs = Inputs(1 << 6);
+    pub const HOLD: Inputs = Inputs(1 << 7);
+
+    pub fn contains(&self, other: Inputs) -> bool {
+        self.0 & other.0 == other.0 && other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: Inputs) {
+        self.0 |= other.0;
+    }
+
+    /// Buttons held now that weren't held in `previous`
+    pub fn pressed_since(&self, previous: Inputs) -> Inputs {
+        Inputs(self.0 & !previous.0)
+    }
+
+    /// Rotation requested by these buttons, clockwise wins over counter-clockwise over 180°
+    pub fn rotation(&self) -> Option<RotationDirection> {
+        if self.contains(Inputs::ROTATE_CW) {
+            Some(RotationDirection::Clockwise)
+        } else if self.contains(Inputs::ROTATE_CCW) {
+            Some(RotationDirection::CounterClockwise)
+        } else if self.contains(Inputs::ROTATE_180) {
+            Some(RotationDirection::OneEighty)
+        } else {
+            None
+        }
+    }
+}
+
+impl BitOr for Inputs {
+    type Output = Inputs;
+
+    fn bitor(self, rhs: Inputs) -> Inputs {
+        Inputs(self.0 | rhs.0)
+    }
+}
+
+/// Everything that happened during a step, in the order it happened
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    Spawned(Tetromino),
+    /// The falling tetromino moved by the given offset, by gravity, shifting or dropping
+    Moved { x: i32, y: i32 },
+    /// The falling tetromino rotated, `kick` is the index of the used kick, 0 being the basic rotation
+    Rotated { direction: RotationDirection, kick: usize },
+    /// The falling tetromino went into the hold queue
+    Held(Tetromino),
+    Locked { tetromino: Tetromino, spin: Spin },
+    Scored(Scored),
+    /// Full rows were found after a lock, `rows` are their indices, they stay until the line clear delay is over
+    LinesCleared { rows: Vec<usize>, kind: ClearKind },
+    /// The full rows got removed from the field
+    RowsRemoved(usize),
+    LevelUp(u32),
+    GameOver(TopOut),
+}
+
+/// Ticks counted up to a fixed duration
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Delay {
+    elapsed: u32,
+    duration: u32,
+}
+
+impl Delay {
+    pub fn new(duration: u32) -> Self {
+        Delay { elapsed: 0, duration }
+    }
+
+    pub fn tick(&mut self) -> &Self {
+        self.elapsed = (self.elapsed + 1).min(self.duration);
+        self
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// How much of the delay is over, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.duration == 0 {
+            1.0
+        } else {
+            self.elapsed as f32 / self.duration as f32
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0;
+    }
+
+    pub fn finish(&mut self) {
+        self.elapsed = self.duration;
+    }
+}
+
+/// What the game is doing between two tetrominoes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Phase {
+    /// A tetromino is falling or lying on the stack until it locks
+    Falling,
+    /// Full rows stay on the field until the delay is over, so they can be animated
+    LineClear { rows: Vec<usize>, delay: Delay },
+    /// Entry delay (ARE), the next tetromino spawns once it is over
+    Entry(Delay),
+}
+
+impl Default for Phase {
+    fn default() -> Self {
+        Phase::Entry(Delay::new(0))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LockDelay {
+    pub delay: Delay,
+    pub resets: u32,
+    pub lowest_row: i32,
+}
+
+impl LockDelay {
+    pub const DURATION: f32 = 0.5;
+    pub const MAX_RESETS: u32 = 15;
+
+    pub fn new(config: &GameConfig) -> Self {
+        LockDelay {
+            delay: Delay::new(config.ticks(Self::DURATION)),
+            resets: 0,
+            lowest_row: i32::MAX,
+        }
+    }
+
+    /// Called after a successful shift or rotation
+    pub fn moved(&mut self, mode: LockDownMode) {
+        match mode {
+            LockDownMode::Extended if self.resets < Self::MAX_RESETS => {
+                self.resets += 1;
+                self.delay.reset();
+            }
+            LockDownMode::Infinite => self.delay.reset(),
+            _ => {}
+        }
+    }
+
+    /// Called after the piece moved down, resets everything once a new lowest row is reached
+    pub fn stepped_down(&mut self, positions: &[Position]) {
+        let row = positions.iter().map(|p| p.y).min().unwrap_or(self.lowest_row);
+        if row < self.lowest_row {
+            self.lowest_row = row;
+            self.resets = 0;
+            self.delay.reset();
+        }
+    }
+
+    pub fn lock_now(&mut self) {
+        self.delay.finish();
+    }
+
+    pub fn expired(&self, mode: LockDownMode) -> bool {
+        self.delay.finished() || (mode == LockDownMode::Extended && self.resets >= Self::MAX_RESETS)
+    }
+}
+
+/// Gravity in G (rows per frame at 60 frames per second).
+/// Rows are counted in fixed point, so partial rows carry over to the next tick the same way on every machine.
+#[derive(Debug, Clone)]
+pub struct Gravity {
+    pub g: f32,
+    /// Fractions of a row added every tick
+    per_tick: u64,
+    /// Same while soft dropping
+    soft_drop_per_tick: u64,
+    rows: u64,
+}
+
+impl Gravity {
+    /// 20G moves pieces to the bottom instantly, anything above that makes no difference
+    pub const MAX: f32 = 20.0;
+    /// One row in the fixed point representation
+    const ROW: u64 = 1 << 16;
+
+    pub fn new(config: &GameConfig, score: &Score) -> Self {
+        let g = config.gravity.unwrap_or(score.gravity()).min(Self::MAX);
+        let rows_per_tick = |g: f32| (g * 60.0 / config.tick_rate as f32 * Self::ROW as f32).round() as u64;
+        Gravity {
+            g,
+            per_tick: rows_per_tick(g),
+            soft_drop_per_tick: rows_per_tick((g * config.soft_drop_factor).min(Self::MAX)),
+            rows: 0,
+        }
+    }
+
+    /// Whole rows to fall this tick
+    fn fall(&mut self, per_tick: u64) -> usize {
+        self.rows += per_tick;
+        let rows = self.rows / Self::ROW;
+        self.rows %= Self::ROW;
+        rows as usize
+    }
+}
+
+/// Auto repeat state of the held movement buttons, counted in ticks
+#[derive(Debug, Clone, Default)]
+struct AutoShift {
+    /// -1 for left, 1 for right, 0 if no direction is held
+    direction: i32,
+    charge: u32,
+    repeat: u32,
+    cut: u32,
+}
+
+/// A whole game, advanced tick by tick with [`Game::tick`] and the buttons held during it.
+/// The same config, seed and inputs for every tick always lead to the same game.
+/// All timings are counted in ticks, seconds in the config are rounded to whole ticks.
+#[derive(Debug, Clone)]
+pub struct Game {
+    config: GameConfig,
+    board: TetrisGame,
+    queue: TetroQueue,
+    active: Option<(Tetr, LockDelay)>,
+    phase: Phase,
+    gravity: Gravity,
+    auto_shift: AutoShift,
+    previous: Inputs,
+    /// Buttons pressed while no tetromino was falling, only these count for IRS and IHS
+    entry_presses: Inputs,
+    over: Option<TopOut>,
+    ticks: u64,
+    /// Seconds passed to [`Game::step`] that didn't fill a whole tick yet
+    leftover: f32,
+}
+
+impl Game {
+    pub fn new(config: GameConfig, seed: u64) -> Self {
+        let board = TetrisGame {
+            score: Score::new(config.mode.scoring(), config.start_level),
+            ..TetrisGame::new(config.width, config.visible_height, config.buffer_height)
+        };
+        let mut queue = TetroQueue::new(config.randomizer, seed);
+        queue.fill_to(config.preview);
+        Game {
+            gravity: Gravity::new(&config, &board.score),
+            config,
+            board,
+            queue,
+            active: None,
+            phase: Phase::default(),
+            auto_shift: AutoShift::default(),
+            previous: Inputs::NONE,
+            entry_presses: Inputs::NONE,
+            over: None,
+            ticks: 0,
+            leftover: 0.0,
+        }
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn board(&self) -> &TetrisGame {
+        &self.board
+    }
+
+    pub fn score(&self) -> &Score {
+        &self.board.score
+    }
+
+    pub fn queue(&self) -> &TetroQueue {
+        &self.queue
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.queue.seed()
+    }
+
+    /// The falling tetromino, `None` during line clears and entry delays
+    pub fn active(&self) -> Option<&Tetr> {
+        self.active.as_ref().map(|(tetr, _)| tetr)
+    }
+
+    pub fn phase(&self) -> &Phase {
+        &self.phase
+    }
+
+    pub fn gravity(&self) -> f32 {
+        self.gravity.g
+    }
+
+    /// How the game ended, `None` while it is still running
+    pub fn over(&self) -> Option<TopOut> {
+        self.over
+    }
+
+    /// Ticks simulated so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Runs as many ticks as fit into `dt` seconds and what was left over from earlier steps.
+    /// Only the ticks themselves are deterministic, use [`Game::tick`] where that matters.
+    pub fn step(&mut self, inputs: Inputs, dt: f32) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        self.leftover += dt;
+        while self.leftover >= self.config.tick_duration() {
+            self.leftover -= self.config.tick_duration();
+            events.extend(self.tick(inputs));
+        }
+        events
+    }
+
+    /// Advances the game by a single tick with the given buttons held
+    pub fn tick(&mut self, inputs: Inputs) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if self.over.is_some() {
+            return events;
+        }
+
+        if self.active.is_none() {
+            self.entry_presses = self.entry_presses | inputs.pressed_since(self.previous);
+        }
+        // Delays only start counting in the tick after the one they were started in,
+        // so a delay of N ticks means N ticks without a falling tetromino
+        self.advance_phase();
+        self.move_piece(inputs, &mut events);
+        self.hold_piece(inputs, &mut events);
+        self.lock_piece(&mut events);
+        self.remove_rows(&mut events);
+        self.spawn_piece(inputs, &mut events);
+
+        self.previous = inputs;
+        self.ticks += 1;
+        events
+    }
+
+    fn move_piece(&mut self, inputs: Inputs, events: &mut Vec<GameEvent>) {
+        let pressed = inputs.pressed_since(self.previous);
+        let Some((tetr, lock)) = self.active.as_mut() else {
+            return;
+        };
+        let game = &mut self.board;
+        let config = &self.config;
+        let auto_shift = &mut self.auto_shift;
+
+        let gravity = &mut self.gravity;
+        let mut rows = gravity.fall(gravity.per_tick);
+        if gravity.g >= Gravity::MAX {
+            rows = game.height();
+        }
+
+        let mut fallen = 0;
+        for _ in 0..rows {
+            if !tetr.try_shift(game, 0, -1) {
+                break;
+            }
+            lock.stepped_down(&tetr.positions);
+            fallen += 1;
+        }
+        if fallen > 0 {
+            events.push(GameEvent::Moved { x: 0, y: -fallen });
+        }
+
+        let left = inputs.contains(Inputs::LEFT);
+        let right = inputs.contains(Inputs::RIGHT);
+        // The DAS cut runs out whether or not a direction is held, so an old rotation doesn't delay a later charge
+        let cut = auto_shift.cut > 0;
+        auto_shift.cut = auto_shift.cut.saturating_sub(1);
+        let mut shifts = 0;
+        if pressed.contains(Inputs::LEFT) || pressed.contains(Inputs::RIGHT) {
+            auto_shift.direction = if pressed.contains(Inputs::LEFT) { -1 } else { 1 };
+            auto_shift.charge = 0;
+            shifts = 1;
+        } else if (auto_shift.direction == -1 && !left) || (auto_shift.direction == 1 && !right) {
+            // The active direction got released, continue with the other one if it is still held
+            auto_shift.direction = if left { -1 } else if right { 1 } else { 0 };
+            auto_shift.charge = 0;
+        } else if auto_shift.direction != 0 && !cut {
+            let das = config.ticks(config.das);
+            let arr = config.ticks(config.arr);
+            let charged = auto_shift.charge >= das;
+            auto_shift.charge += 1;
+            if auto_shift.charge >= das {
+                if !charged {
+                    auto_shift.repeat = 0;
+                    shifts = 1;
+                } else {
+                    auto_shift.repeat += 1;
+                }
+                if arr == 0 {
+                    shifts = game.width;
+                } else {
+                    while auto_shift.repeat >= arr {
+                        auto_shift.repeat -= arr;
+                        shifts += 1;
+                    }
+                }
+            }
+        }
+
+        let mut shifted = 0;
+        for _ in 0..shifts {
+            if !tetr.try_shift(game, auto_shift.direction, 0) {
+                break;
+            }
+            lock.moved(config.lock_down);
+            shifted += auto_shift.direction;
+        }
+        if shifted != 0 {
+            events.push(GameEvent::Moved { x: shifted, y: 0 });
+        }
+
+        // Soft drop replaces gravity with the faster soft drop gravity, the first row drops right away
+        let mut drops = 0;
+        if pressed.contains(Inputs::SOFT_DROP) {
+            drops = 1;
+        } else if inputs.contains(Inputs::SOFT_DROP) {
+            drops = gravity.fall(gravity.soft_drop_per_tick.saturating_sub(gravity.per_tick)).min(game.height());
+        }
+        if config.soft_drop_factor.is_infinite() && drops > 0 {
+            drops = game.height();
+        }
+
+        let mut dropped = 0;
+        for _ in 0..drops {
+            if !tetr.try_shift(game, 0, -1) {
+                break;
+            }
+            lock.stepped_down(&tetr.positions);
+            dropped += 1;
+        }
+        game.score.award_drop(dropped, false);
+        if dropped > 0 {
+            events.push(GameEvent::Moved { x: 0, y: -(dropped as i32) });
+        }
+
+        if let Some(direction) = pressed.rotation() {
+            if let Some(kick) = tetr.spin(game, direction) {
+                lock.moved(config.lock_down);
+                auto_shift.cut = config.ticks(config.das_cut);
+                events.push(GameEvent::Rotated { direction, kick });
+            }
+        }
+
+        if pressed.contains(Inputs::HARD_DROP) {
+            // Move piece all the way down until it hits something
+            let mut dropped = 0;
+            while tetr.try_shift(game, 0, -1) {
+                dropped += 1;
+            }
+            lock.lock_now();
+            game.score.award_drop(dropped, true);
+            if dropped > 0 {
+                events.push(GameEvent::Moved { x: 0, y: -(dropped as i32) });
+            }
+        }
+    }
+
+    /// Hold Piece
+    /// Pressing hold moves the falling tetromino into the hold queue and releases the previously held one
+    /// (or the next one from the queue if nothing is held) at the top of the playfield.
+    /// A held tetromino can't be swapped again until the falling one locks.
+    /// | Guidelines
+    fn hold_piece(&mut self, inputs: Inputs, events: &mut Vec<GameEvent>) {
+        if !self.board.can_hold || !inputs.pressed_since(self.previous).contains(Inputs::HOLD) {
+            return;
+        }
+        // A tetromino hard dropped in the same tick locks instead of going into hold
+        if self.active.as_ref().is_none_or(|(_, lock)| lock.delay.finished()) {
+            return;
+        }
+        let (tetr, _) = self.active.take().unwrap();
+
+        let next = self.swap_hold(tetr.tetromino);
+        events.push(GameEvent::Held(tetr.tetromino));
+        let released = Tetr::new(next, &self.board);
+        // The released tetromino spawns like any other one, so it can block out as well
+        if !self.board.fits(&released.positions) {
+            self.over = Some(TopOut::BlockOut);
+            events.push(GameEvent::GameOver(TopOut::BlockOut));
+            return;
+        }
+        self.active = Some((released, LockDelay::new(&self.config)));
+        events.push(GameEvent::Spawned(next));
+        self.queue.fill_to(self.config.preview);
+    }
+
+    /// Puts the tetromino into the hold queue and returns the one to continue with
+    fn swap_hold(&mut self, tetromino: Tetromino) -> Tetromino {
+        let next = match self.board.hold.take() {
+            Some(held) => held,
+            None => self.queue.take_next(),
+        };
+        self.board.hold = Some(tetromino);
+        self.board.can_hold = false;
+        next
+    }
+
+    /// Counts down the lock delay while the tetromino lies on the stack, once it is over the tetromino
+    /// gets merged into the field and scored
+    fn lock_piece(&mut self, events: &mut Vec<GameEvent>) {
+        let Some((tetr, lock)) = self.active.as_mut() else {
+            return;
+        };
+        let below = tetr.positions.iter().map(|p| p.offset(0, -1)).collect::<Vec<_>>();
+        if self.board.fits(&below) {
+            return;
+        }
+        lock.delay.tick();
+        if !lock.expired(self.config.lock_down) {
+            return;
+        }
+
+        let (tetr, _) = self.active.take().unwrap();
+        let spin = tetr.spin_kind(&self.board);
+        events.push(GameEvent::Locked { tetromino: tetr.tetromino, spin });
+        if tetr.positions.iter().all(|p| p.y >= self.board.visible_height as i32) {
+            self.over = Some(TopOut::LockOut);
+            events.push(GameEvent::GameOver(TopOut::LockOut));
+            return;
+        }
+        self.board.merge(&tetr);
+
+        // Full rows are scored right away, but only removed once the line clear delay is over
+        let removed_rows = self.board.full_rows();
+        let perfect_clear = !removed_rows.is_empty() && self.board.is_empty_without(&removed_rows);
+        let lines = removed_rows.len() as u32;
+        if let Some(scored) = self.board.score.award_lock(lines, spin, perfect_clear) {
+            events.push(GameEvent::Scored(scored));
+        }
+
+        if let Some(kind) = ClearKind::from_lines(removed_rows.len()) {
+            events.push(GameEvent::LinesCleared { rows: removed_rows.clone(), kind });
+        }
+
+        if self.board.score.increase(lines, spin) {
+            self.gravity = Gravity::new(&self.config, &self.board.score);
+            events.push(GameEvent::LevelUp(self.board.score.level));
+        }
+
+        self.phase = if removed_rows.is_empty() {
+            Phase::Entry(Delay::new(self.config.ticks(self.config.are())))
+        } else {
+            Phase::LineClear { rows: removed_rows, delay: Delay::new(self.config.ticks(self.config.line_clear_delay())) }
+        };
+    }
+
+    /// Runs the delays between two tetrominoes started in an earlier tick
+    fn advance_phase(&mut self) {
+        match &mut self.phase {
+            Phase::LineClear { delay, .. } | Phase::Entry(delay) => {
+                delay.tick();
+            }
+            Phase::Falling => {}
+        }
+    }
+
+    /// Removes the full rows once the line clear delay is over and starts the entry delay,
+    /// right in the tick of the lock if there is no line clear delay
+    fn remove_rows(&mut self, events: &mut Vec<GameEvent>) {
+        let Phase::LineClear { rows, delay } = &mut self.phase else {
+            return;
+        };
+        if delay.finished() {
+            let rows = std::mem::take(rows);
+            self.board.clear_rows(&rows);
+            events.push(GameEvent::RowsRemoved(rows.len()));
+            self.phase = Phase::Entry(Delay::new(self.config.ticks(self.config.are())));
+        }
+    }
+
+    /// Spawns the next tetromino once the entry delay is over.
+    /// Buttons pressed during the delay and still held are applied right away, rotation (IRS) only if the
+    /// rotated tetromino fits. Buttons held down since before the lock don't count, so keeping hold pressed
+    /// doesn't hold every following tetromino too, and without an entry delay there is nothing to apply.
+    fn spawn_piece(&mut self, inputs: Inputs, events: &mut Vec<GameEvent>) {
+        let entry_over = matches!(&self.phase, Phase::Entry(delay) if delay.finished());
+        if !entry_over || self.active.is_some() {
+            return;
+        }
+
+        let initial = Inputs(inputs.0 & std::mem::take(&mut self.entry_presses).0);
+        let mut tetromino = self.queue.take_next();
+        self.board.can_hold = true;
+        if self.config.ihs && initial.contains(Inputs::HOLD) {
+            events.push(GameEvent::Held(tetromino));
+            tetromino = self.swap_hold(tetromino);
+        }
+
+        let mut tetr = Tetr::new(tetromino, &self.board);
+        // check if the piece can be spawned
+        if !self.board.fits(&tetr.positions) {
+            self.over = Some(TopOut::BlockOut);
+            events.push(GameEvent::GameOver(TopOut::BlockOut));
+            return;
+        }
+        events.push(GameEvent::Spawned(tetromino));
+        if self.config.irs {
+            if let Some(direction) = initial.rotation() {
+                if let Some(kick) = tetr.spin(&self.board, direction) {
+                    events.push(GameEvent::Rotated { direction, kick });
+                }
+            }
+        }
+        // At 20G pieces already spawn on top of the stack
+        if self.gravity.g >= Gravity::MAX {
+            let mut fallen = 0;
+            while tetr.try_shift(&self.board, 0, -1) {
+                fallen += 1;
+            }
+            if fallen > 0 {
+                events.push(GameEvent::Moved { x: 0, y: -fallen });
+            }
+        }
+        self.active = Some((tetr, LockDelay::new(&self.config)));
+        self.phase = Phase::Falling;
+        self.queue.fill_to(self.config.preview);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+    use crate::randomizer::RandomizerKind;
+
+    #[test]
+    fn hold_blocks_out_when_spawn_is_covered() {
+        let mut game = Game::new(GameConfig::default(), 1);
+        game.tick(Inputs::NONE);
+        let active = game.active().unwrap().positions.clone();
+        for y in 20..24 {
+            for x in 0..game.board.width {
+                if !active.contains(&Position { x: x as i32, y: y as i32 }) {
+                    game.board.field[y][x] = Cell::Garbage;
+                }
+            }
+        }
+
+        let events = game.tick(Inputs::HOLD);
+        assert_eq!(game.over(), Some(TopOut::BlockOut));
+        assert!(events.contains(&GameEvent::GameOver(TopOut::BlockOut)));
+        assert!(game.active().is_none());
+    }
+
+    fn spawned(events: &[GameEvent]) -> bool {
+        events.iter().any(|e| matches!(e, GameEvent::Spawned(_)))
+    }
+
+    /// Hard drops the first tetromino and returns the tick it locked in and the tick the next one spawned in
+    fn lock_and_respawn(game: &mut Game) -> (u64, u64) {
+        game.tick(Inputs::NONE);
+        let locked = game.ticks();
+        let events = game.tick(Inputs::HARD_DROP);
+        assert!(events.iter().any(|e| matches!(e, GameEvent::Locked { .. })));
+        if spawned(&events) {
+            return (locked, locked);
+        }
+        loop {
+            let tick = game.ticks();
+            if spawned(&game.tick(Inputs::NONE)) {
+                return (locked, tick);
+            }
+            assert!(tick < locked + 100, "the next piece never spawned");
+        }
+    }
+
+    #[test]
+    fn entry_delay_lasts_exactly_its_ticks() {
+        for are in [0, 1, 2, 10] {
+            let config = GameConfig { are: Some(are as f32 / 60.0), ..GameConfig::default() };
+            let (locked, spawned) = lock_and_respawn(&mut Game::new(config, 3));
+            assert_eq!(spawned, locked + are, "ARE of {are} ticks");
+        }
+    }
+
+    #[test]
+    fn line_clear_delay_lasts_exactly_its_ticks() {
+        for (line_clear, are) in [(0, 0), (1, 0), (18, 0), (18, 10)] {
+            let config = GameConfig {
+                line_clear_delay: Some(line_clear as f32 / 60.0),
+                are: Some(are as f32 / 60.0),
+                ..GameConfig::default()
+            };
+            let mut game = Game::new(config, 3);
+            // The bottom row is full once the first tetromino lands, whichever one it is
+            let first = Tetr::new(*game.queue().get(0).unwrap(), game.board());
+            let landed = first.ghost_positions(game.board());
+            for x in 0..game.board.width {
+                if !landed.contains(&Position { x: x as i32, y: 0 }) {
+                    game.board.field[0][x] = Cell::Garbage;
+                }
+            }
+
+            let (locked, spawned) = lock_and_respawn(&mut game);
+            assert_eq!(game.score().lines, 1);
+            assert_eq!(spawned, locked + line_clear + are, "line clear delay of {line_clear} and ARE of {are} ticks");
+        }
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_game() {
+        let play = |seed: u64| {
+            let mut game = Game::new(GameConfig::default(), seed);
+            let mut events = Vec::new();
+            let mut rng = seed;
+            for tick in 0..5000u64 {
+                rng = rng.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let inputs = if tick % 5 == 0 { Inputs((rng >> 40) as u16 & 0xff) } else { Inputs::NONE };
+                events.extend(game.tick(inputs));
+            }
+            (events, game.board().checksum(), game.score().points, game.score().lines, game.ticks())
+        };
+
+        for seed in [0, 1, 42] {
+            let (events, board, points, lines, ticks) = play(seed);
+            assert!(events.iter().any(|e| matches!(e, GameEvent::Locked { .. })));
+            assert_eq!(play(seed), (events, board, points, lines, ticks));
+        }
+        assert_ne!(play(1).1, play(2).1);
+    }
+
+    fn held(events: &[GameEvent]) -> bool {
+        events.iter().any(|e| matches!(e, GameEvent::Held(_)))
+    }
+
+    #[test]
+    fn holding_hold_down_does_not_hold_the_next_piece() {
+        let mut game = Game::new(GameConfig::default(), 1);
+        game.tick(Inputs::NONE);
+        assert!(held(&game.tick(Inputs::HOLD)));
+
+        // Hard drop with hold still down, without an entry delay the next piece spawns in the same tick
+        let events = game.tick(Inputs::HOLD | Inputs::HARD_DROP);
+        assert!(events.iter().any(|e| matches!(e, GameEvent::Spawned(_))));
+        assert!(!held(&events));
+        assert!(!held(&game.tick(Inputs::HOLD)));
+    }
+
+    #[test]
+    fn hold_pressed_during_entry_delay_is_applied_at_spawn() {
+        let config = GameConfig { are: Some(0.1), ..GameConfig::default() };
+        let mut game = Game::new(config, 1);
+        game.tick(Inputs::NONE);
+        game.tick(Inputs::HARD_DROP);
+        assert!(game.active().is_none());
+
+        game.tick(Inputs::NONE);
+        let mut ticks = 0;
+        loop {
+            let events = game.tick(Inputs::HOLD);
+            if events.iter().any(|e| matches!(e, GameEvent::Spawned(_))) {
+                assert!(held(&events));
+                break;
+            }
+            ticks += 1;
+            assert!(ticks < 60, "the next piece never spawned");
+        }
+    }
+
+    #[test]
+    fn hold_during_entry_delay_with_a_single_preview_holds_the_next_piece() {
+        for seed in 0..5 {
+            let config = GameConfig { preview: 1, are: Some(0.1), randomizer: RandomizerKind::Tgm, ..GameConfig::default() };
+            let mut expected = TetroQueue::new(RandomizerKind::Tgm, seed);
+            expected.fill_to(3);
+            let mut game = Game::new(config, seed);
+            game.tick(Inputs::NONE);
+            game.tick(Inputs::HARD_DROP);
+
+            game.tick(Inputs::NONE);
+            while !spawned(&game.tick(Inputs::HOLD)) {
+                assert!(game.ticks() < 60, "the next piece never spawned");
+            }
+            assert_eq!(game.board().hold, expected.get(1).copied(), "seed {seed}");
+            assert_eq!(game.active().map(|t| t.tetromino), expected.get(2).copied(), "seed {seed}");
+        }
+    }
+
+    /// Ticks, counted from the first one, in which the tetromino moved sideways while the inputs were held
+    fn shift_ticks(game: &mut Game, inputs: &[Inputs]) -> Vec<usize> {
+        let mut ticks = Vec::new();
+        for (tick, inputs) in inputs.iter().enumerate() {
+            if game.tick(*inputs).iter().any(|e| matches!(e, GameEvent::Moved { x, .. } if *x != 0)) {
+                ticks.push(tick);
+            }
+        }
+        ticks
+    }
+
+    fn auto_shift_game(das: u32, arr: u32, das_cut: u32) -> Game {
+        let config = GameConfig {
+            das: das as f32 / 60.0,
+            arr: arr as f32 / 60.0,
+            das_cut: das_cut as f32 / 60.0,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(config, 1);
+        game.tick(Inputs::NONE);
+        game
+    }
+
+    #[test]
+    fn holding_a_direction_charges_das_then_repeats_every_arr() {
+        let mut game = auto_shift_game(10, 2, 0);
+        assert_eq!(shift_ticks(&mut game, &[Inputs::RIGHT; 16]), [0, 10, 12, 14]);
+    }
+
+    #[test]
+    fn arr_of_zero_moves_to_the_wall_once_charged() {
+        let mut game = auto_shift_game(10, 0, 0);
+        assert_eq!(shift_ticks(&mut game, &[Inputs::LEFT; 12]), [0, 10]);
+        assert!(game.active().unwrap().positions.iter().any(|p| p.x == 0));
+    }
+
+    #[test]
+    fn releasing_a_direction_resets_the_charge() {
+        let mut game = auto_shift_game(10, 2, 0);
+        let mut inputs = vec![Inputs::RIGHT; 8];
+        inputs.extend([Inputs::NONE; 2]);
+        inputs.extend([Inputs::RIGHT; 12]);
+        assert_eq!(shift_ticks(&mut game, &inputs), [0, 10, 20]);
+    }
+
+    #[test]
+    fn rotating_cuts_das_for_a_while() {
+        let mut game = auto_shift_game(10, 2, 6);
+        let mut inputs = vec![Inputs::RIGHT; 5];
+        inputs.push(Inputs::RIGHT | Inputs::ROTATE_CW);
+        inputs.extend([Inputs::RIGHT; 12]);
+        // The charge stops for the 6 ticks after the rotation
+        assert_eq!(shift_ticks(&mut game, &inputs), [0, 16]);
+    }
+
+    #[test]
+    fn das_cut_runs_out_without_a_direction_held() {
+        let mut game = auto_shift_game(6, 0, 30);
+        game.tick(Inputs::ROTATE_CW);
+        for _ in 0..100 {
+            game.tick(Inputs::NONE);
+        }
+        assert_eq!(shift_ticks(&mut game, &[Inputs::LEFT; 8]), [0, 6]);
+    }
+
+    #[test]
+    fn hard_drop_and_hold_in_the_same_tick_locks() {
+        let mut game = Game::new(GameConfig::default(), 1);
+        game.tick(Inputs::NONE);
+        let tetromino = game.active().unwrap().tetromino;
+        let events = game.tick(Inputs::HARD_DROP | Inputs::HOLD);
+        assert!(!held(&events));
+        assert!(events.contains(&GameEvent::Locked { tetromino, spin: Spin::None }));
+        assert_eq!(game.board().hold, None);
+        assert!(game.board().field.iter().flatten().any(|c| *c != Cell::Empty));
+    }
+
+    #[test]
+    fn extended_placement_locks_on_the_ground_after_fifteen_resets() {
+        let config = GameConfig { gravity: Some(Gravity::MAX), ..GameConfig::default() };
+        let mut game = Game::new(config, 1);
+        game.tick(Inputs::NONE);
+        // Every press shifts the tetromino on the floor, long before the lock delay itself is over
+        for shift in 1..=LockDelay::MAX_RESETS {
+            let direction = if shift % 2 == 0 { Inputs::LEFT } else { Inputs::RIGHT };
+            let events = game.tick(direction);
+            assert!(events.iter().any(|e| matches!(e, GameEvent::Moved { x, .. } if *x != 0)), "shift {shift}");
+            let locked = events.iter().any(|e| matches!(e, GameEvent::Locked { .. }));
+            assert_eq!(locked, shift == LockDelay::MAX_RESETS, "shift {shift}");
+            game.tick(Inputs::NONE);
+        }
+    }
+
+    fn at_row(y: i32) -> [Position; 1] {
+        [Position { x: 0, y }]
+    }
+
+    #[test]
+    fn extended_placement_resets_the_count_on_a_new_lowest_row() {
+        let mut lock = LockDelay::new(&GameConfig::default());
+        lock.stepped_down(&at_row(10));
+        for _ in 0..LockDelay::MAX_RESETS {
+            assert!(!lock.expired(LockDownMode::Extended));
+            lock.moved(LockDownMode::Extended);
+        }
+        assert!(lock.expired(LockDownMode::Extended));
+
+        // Falling back to a row it already reached doesn't give the resets back, a lower one does
+        lock.stepped_down(&at_row(10));
+        assert!(lock.expired(LockDownMode::Extended));
+        lock.stepped_down(&at_row(9));
+        assert_eq!(lock.resets, 0);
+        assert!(!lock.expired(LockDownMode::Extended));
+    }
+
+    #[test]
+    fn infinite_placement_never_locks_while_moving() {
+        let mut lock = LockDelay::new(&GameConfig::default());
+        for _ in 0..100 {
+            for _ in 1..lock.delay.duration {
+                lock.delay.tick();
+            }
+            assert!(!lock.expired(LockDownMode::Infinite));
+            lock.moved(LockDownMode::Infinite);
+        }
+        assert_eq!(lock.delay.elapsed, 0);
+    }
+
+    #[test]
+    fn step_reset_ignores_shifts_and_rotations() {
+        let mut lock = LockDelay::new(&GameConfig::default());
+        lock.stepped_down(&at_row(10));
+        for _ in 1..lock.delay.duration {
+            lock.delay.tick();
+            lock.moved(LockDownMode::StepReset);
+        }
+        assert!(!lock.expired(LockDownMode::StepReset));
+        lock.delay.tick();
+        assert!(lock.expired(LockDownMode::StepReset));
+
+        lock.stepped_down(&at_row(9));
+        assert!(!lock.expired(LockDownMode::StepReset));
+    }
+}
@@ -0,0 +1,21 @@
+//! Rules of the game without any windowing or rendering, so games can be simulated headless.
+//!
+//! A [`Game`] is created from a [`GameConfig`] and a seed and advanced one fixed tick at a time with
+//! [`Game::tick`], which takes the buttons held and returns what happened. Since nothing else goes in,
+//! a [`Replay`] of the seed, the config and the inputs of every tick is enough to play a game again.
+
+pub mod board;
+pub mod config;
+pub mod game;
+pub mod piece;
+pub mod randomizer;
+pub mod replay;
+pub mod scoring;
+
+pub use board::{Cell, TetrisGame};
+pub use config::{GameConfig, GameMode, LockDownMode};
+pub use game::{Delay, Game, GameEvent, Inputs, Phase, TopOut};
+pub use piece::{Position, Rotation, RotationDirection, Spin, Tetr, Tetromino};
+pub use randomizer::{RandomizerKind, TetroQueue};
+pub use replay::{Replay, ReplayError, ReplayResult, Timeline};
+pub use scoring::{ClearKind, Score, Scored, ScoringSystem};
@@ -0,0 +1,699 @@
+use std::fmt::{Display, Formatter};
+use crate::board::TetrisGame;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    pub fn offset(&self, x: i32, y: i32) -> Position {
+        Position { x: self.x + x, y: self.y + y }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rotation {
+    Zero = 0,
+    Ninety = 1,
+    OneEighty = 2,
+    TwoHundredSeventy = 3,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RotationDirection {
+    Clockwise,
+    CounterClockwise,
+    OneEighty,
+}
+
+impl Rotation {
+    pub fn clockwise(&self) -> Rotation {
+        match self {
+            Rotation::Zero => Rotation::Ninety,
+            Rotation::Ninety => Rotation::OneEighty,
+            Rotation::OneEighty => Rotation::TwoHundredSeventy,
+            Rotation::TwoHundredSeventy => Rotation::Zero,
+        }
+    }
+
+    pub fn counter_clockwise(&self) -> Rotation {
+        match self {
+            Rotation::Zero => Rotation::TwoHundredSeventy,
+            Rotation::Ninety => Rotation::Zero,
+            Rotation::OneEighty => Rotation::Ninety,
+            Rotation::TwoHundredSeventy => Rotation::OneEighty,
+        }
+    }
+
+    pub fn rotate(&self, direction: RotationDirection) -> Rotation {
+        match direction {
+            RotationDirection::Clockwise => self.clockwise(),
+            RotationDirection::CounterClockwise => self.counter_clockwise(),
+            RotationDirection::OneEighty => self.clockwise().clockwise(),
+        }
+    }
+}
+
+/// Wall Kicks
+/// When the player attempts to rotate a tetromino, but the position it would normally occupy after basic rotation is obstructed,
+/// (either by the wall or floor of the playfield, or by the stack), the game will attempt to "kick" the tetromino into an alternative position nearby.
+/// If none of the 5 positions are available, the rotation fails completely.
+/// The J, L, S, T and Z tetrominoes all share the same kick data, the I tetromino has its own, the O tetromino does not kick.
+/// | SRS
+///
+/// Tables are for the clockwise transitions starting at the given rotation, positive y being up.
+/// A counter-clockwise transition is the negated clockwise transition in the other direction (R->0 = -(0->R)).
+const JLSTZ_KICKS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 0->R
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R->2
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 2->L
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L->0
+];
+
+const I_KICKS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],  // 0->R
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],  // R->2
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],  // 2->L
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],  // L->0
+];
+
+/// 180° rotations are not part of SRS, modern clients (TETR.IO's SRS+) kick them with their own table.
+/// It is shared by all tetrominoes and indexed by the starting rotation.
+const ONE_EIGHTY_KICKS: [[(i32, i32); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],     // 0->2
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],       // R->L
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],  // 2->0
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],    // L->R
+];
+
+// https://tetris.fandom.com/wiki/Tetris_Guideline
+// https://tetris.fandom.com/wiki/SRS
+// We workin' by the Guidelines
+// Therefore no creativity is needed
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tetromino {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+}
+
+impl Display for Tetromino {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tetromino::I => write!(f, "I"),
+            Tetromino::O => write!(f, "O"),
+            Tetromino::T => write!(f, "T"),
+            Tetromino::S => write!(f, "S"),
+            Tetromino::Z => write!(f, "Z"),
+            Tetromino::J => write!(f, "J"),
+            Tetromino::L => write!(f, "L"),
+        }
+    }
+}
+
+impl Tetromino {
+    /// Cyan I,
+    /// Yellow O,
+    /// Purple T,
+    /// Green S,
+    /// Red Z,
+    /// Blue J,
+    /// Orange L
+    /// | Guidelines
+    ///
+    /// RGB format, no need for alpha
+    pub fn color(&self) -> [f32; 3] {
+        match self {
+            Tetromino::I => [0.0, 1.0, 1.0],
+            Tetromino::O => [1.0, 1.0, 0.0],
+            Tetromino::T => [1.0, 0.0, 1.0],
+            Tetromino::S => [0.0, 1.0, 0.0],
+            Tetromino::Z => [1.0, 0.0, 0.0],
+            Tetromino::J => [0.0, 0.0, 1.0],
+            Tetromino::L => [1.0, 0.5, 0.0],
+        }
+    }
+
+    /// Tetromino start locations
+    /// The I and O spawn in the middle columns
+    /// The rest spawn in the left-middle columns
+    /// The tetriminoes spawn horizontally with J, L and T spawning flat-side first.
+    /// Spawn above playfield, row 21 for I, and 21/22 for all other tetriminoes.
+    /// Immediately drop one space if no existing Block is in its path
+    /// | Guidelines
+    // TODO: we can potentially switch the vec for a custom struct, but that's for later
+    //       Okay, maybe we should, as that would be wayyyy easier to work with,
+    //       but that's for later
+    ///
+    /// These are the positions for the standard 10×20 playfield, see [`Tetromino::spawn_positions`].
+    pub fn start_positions(&self) -> Vec<Position> {
+        match self {
+            Tetromino::I => {
+                vec![
+                    Position { x: 3, y: 21 },
+                    Position { x: 4, y: 21 },
+                    Position { x: 5, y: 21 },
+                    Position { x: 6, y: 21 },
+                ]
+            }
+            Tetromino::O => {
+                vec![
+                    Position { x: 4, y: 21 },
+                    Position { x: 5, y: 21 },
+                    Position { x: 4, y: 22 },
+                    Position { x: 5, y: 22 },
+                ]
+            }
+            Tetromino::T => {
+                vec![
+                    Position { x: 3, y: 21 },
+                    Position { x: 4, y: 21 },
+                    Position { x: 5, y: 21 },
+                    Position { x: 4, y: 22 },
+                ]
+            }
+            Tetromino::S => {
+                vec![
+                    Position { x: 3, y: 21 },
+                    Position { x: 4, y: 21 },
+                    Position { x: 4, y: 22 },
+                    Position { x: 5, y: 22 },
+                ]
+            }
+            Tetromino::Z => {
+                vec![
+                    Position { x: 3, y: 22 },
+                    Position { x: 4, y: 22 },
+                    Position { x: 4, y: 21 },
+                    Position { x: 5, y: 21 },
+                ]
+            }
+            Tetromino::J => {
+                vec![
+                    Position { x: 3, y: 22 },
+                    Position { x: 3, y: 21 },
+                    Position { x: 4, y: 21 },
+                    Position { x: 5, y: 21 },
+                ]
+            }
+            Tetromino::L => {
+                vec![
+                    Position { x: 3, y: 21 },
+                    Position { x: 4, y: 21 },
+                    Position { x: 5, y: 21 },
+                    Position { x: 5, y: 22 },
+                ]
+            }
+        }
+    }
+
+    /// Basic Rotation
+    /// The basic rotation states are shown in the diagram on the right. Some points to note:
+    ///
+    /// When unobstructed, the tetrominoes all appear to rotate purely about a single point. These apparent rotation centers are shown as circles in the diagram.
+    /// It is a pure rotation in a mathematical sense.
+    /// As a direct consequence, the J, L, S, T and Z tetrominoes have 1 of their 4 states (the spawn state) in a "floating" position where they are not in contact with the bottom of their bounding box.
+    /// This allows the bounding box to descend below the surface of the stack (or the floor of the playing field) making it impossible for the tetrominoes to be rotated without the aid of floor kicks.
+    /// The S, Z and I tetrominoes have two horizontally oriented states and two vertically oriented states. It can be argued that having two vertical states leads to faster finesse.
+    /// For the "I" and "O" tetrominoes, the apparent rotation center is at the intersection of gridlines, whereas for the "J", "L", "S", "T" and "Z" tetrominoes, the rotation center coincides with the center of one of the four constituent minos.
+    /// | Guidelines
+    pub fn try_basic_rotation(
+        &self,
+        positions: &[Position],
+        current_rotation: &Rotation,
+        direction: RotationDirection,
+    ) -> Vec<Position> {
+        match direction {
+            RotationDirection::Clockwise => self.basic_clockwise_rotation(positions, current_rotation),
+            RotationDirection::CounterClockwise => {
+                // Undo the clockwise rotation which leads into the current rotation
+                let undone = self.basic_clockwise_rotation(positions, &current_rotation.counter_clockwise());
+                positions
+                    .iter()
+                    .zip(undone)
+                    .map(|(p, u)| Position { x: 2 * p.x - u.x, y: 2 * p.y - u.y })
+                    .collect()
+            }
+            RotationDirection::OneEighty => {
+                let once = self.basic_clockwise_rotation(positions, current_rotation);
+                self.basic_clockwise_rotation(&once, &current_rotation.clockwise())
+            }
+        }
+    }
+
+    fn basic_clockwise_rotation(
+        &self,
+        positions: &[Position],
+        current_rotation: &Rotation,
+    ) -> Vec<Position> {
+        let mut new_positions = positions.to_owned();
+        match self {
+            Tetromino::I => {
+                match current_rotation {
+                    Rotation::Zero => {
+                        new_positions[0].x += 2;
+                        new_positions[0].y += 1;
+                        new_positions[1].x += 1;
+                        new_positions[2].y -= 1;
+                        new_positions[3].x -= 1;
+                        new_positions[3].y -= 2;
+                    }
+                    Rotation::Ninety => {
+                        new_positions[0].x += 1;
+                        new_positions[0].y -= 2;
+                        new_positions[1].y -= 1;
+                        new_positions[2].x -= 1;
+                        new_positions[3].x -= 2;
+                        new_positions[3].y += 1;
+                    }
+                    Rotation::OneEighty => {
+                        new_positions[0].x -= 2;
+                        new_positions[0].y -= 1;
+                        new_positions[1].x -= 1;
+                        new_positions[2].y += 1;
+                        new_positions[3].x += 1;
+                        new_positions[3].y += 2;
+                    }
+                    Rotation::TwoHundredSeventy => {
+                        new_positions[0].x -= 1;
+                        new_positions[0].y += 2;
+                        new_positions[1].y += 1;
+                        new_positions[2].x += 1;
+                        new_positions[3].x += 2;
+                        new_positions[3].y -= 1;
+                    }
+                }
+            }
+            Tetromino::O => {}
+            Tetromino::T => {
+                match current_rotation {
+                    Rotation::Zero => {
+                        new_positions[0].x += 1;
+                        new_positions[0].y += 1;
+                        new_positions[2].x -= 1;
+                        new_positions[2].y -= 1;
+                        new_positions[3].x += 1;
+                        new_positions[3].y -= 1;
+                    }
+                    Rotation::Ninety => {
+                        new_positions[0].x += 1;
+                        new_positions[0].y -= 1;
+                        new_positions[2].x -= 1;
+                        new_positions[2].y += 1;
+                        new_positions[3].x -= 1;
+                        new_positions[3].y -= 1;
+                    }
+                    Rotation::OneEighty => {
+                        new_positions[0].x -= 1;
+                        new_positions[0].y -= 1;
+                        new_positions[2].x += 1;
+                        new_positions[2].y += 1;
+                        new_positions[3].x -= 1;
+                        new_positions[3].y += 1;
+                    }
+                    Rotation::TwoHundredSeventy => {
+                        new_positions[0].x -= 1;
+                        new_positions[0].y += 1;
+                        new_positions[2].x += 1;
+                        new_positions[2].y -= 1;
+                        new_positions[3].x += 1;
+                        new_positions[3].y += 1;
+                    }
+                }
+            }
+            Tetromino::S => {
+                match current_rotation {
+                    Rotation::Zero => {
+                        new_positions[0].x += 1;
+                        new_positions[0].y += 1;
+                        new_positions[2].x += 1;
+                        new_positions[2].y -= 1;
+                        new_positions[3].y -= 2;
+                    }
+                    Rotation::Ninety => {
+                        new_positions[0].x += 1;
+                        new_positions[0].y -= 1;
+                        new_positions[2].x -= 1;
+                        new_positions[2].y -= 1;
+                        new_positions[3].x -= 2;
+                    }
+                    Rotation::OneEighty => {
+                        new_positions[0].x -= 1;
+                        new_positions[0].y -= 1;
+                        new_positions[2].x -= 1;
+                        new_positions[2].y += 1;
+                        new_positions[3].y += 2;
+                    }
+                    Rotation::TwoHundredSeventy => {
+                        new_positions[0].x -= 1;
+                        new_positions[0].y += 1;
+                        new_positions[2].x += 1;
+                        new_positions[2].y += 1;
+                        new_positions[3].x += 2;
+                    }
+                }
+            }
+            Tetromino::Z => {
+                match current_rotation {
+                    Rotation::Zero => {
+                        new_positions[0].x += 2;
+                        new_positions[1].x += 1;
+                        new_positions[1].y -= 1;
+                        new_positions[3].x -= 1;
+                        new_positions[3].y -= 1;
+                    }
+                    Rotation::Ninety => {
+                        new_positions[0].y -= 2;
+                        new_positions[1].x -= 1;
+                        new_positions[1].y -= 1;
+                        new_positions[3].x -= 1;
+                        new_positions[3].y += 1;
+                    }
+                    Rotation::OneEighty => {
+                        new_positions[0].x -= 2;
+                        new_positions[1].x -= 1;
+                        new_positions[1].y += 1;
+                        new_positions[3].x += 1;
+                        new_positions[3].y += 1;
+                    }
+                    Rotation::TwoHundredSeventy => {
+                        new_positions[0].y += 2;
+                        new_positions[1].x += 1;
+                        new_positions[1].y += 1;
+                        new_positions[3].x += 1;
+                        new_positions[3].y -= 1;
+                    }
+                }
+            }
+            Tetromino::J => {
+                match current_rotation {
+                    Rotation::Zero => {
+                        new_positions[0].x += 2;
+                        new_positions[1].x += 1;
+                        new_positions[1].y += 1;
+                        new_positions[3].x -= 1;
+                        new_positions[3].y -= 1;
+                    }
+                    Rotation::Ninety => {
+                        new_positions[0].y -= 2;
+                        new_positions[1].x += 1;
+                        new_positions[1].y -= 1;
+                        new_positions[3].x -= 1;
+                        new_positions[3].y += 1;
+                    }
+                    Rotation::OneEighty => {
+                        new_positions[0].x -= 2;
+                        new_positions[1].x -= 1;
+                        new_positions[1].y -= 1;
+                        new_positions[3].x += 1;
+                        new_positions[3].y += 1;
+                    }
+                    Rotation::TwoHundredSeventy => {
+                        new_positions[0].y += 2;
+                        new_positions[1].x -= 1;
+                        new_positions[1].y += 1;
+                        new_positions[3].x += 1;
+                        new_positions[3].y -= 1;
+                    }
+                }
+            }
+            Tetromino::L => {
+                match current_rotation {
+                    Rotation::Zero => {
+                        new_positions[0].x += 1;
+                        new_positions[0].y += 1;
+                        new_positions[2].x -= 1;
+                        new_positions[2].y -= 1;
+                        new_positions[3].y -= 2;
+                    }
+                    Rotation::Ninety => {
+                        new_positions[0].x += 1;
+                        new_positions[0].y -= 1;
+                        new_positions[2].x -= 1;
+                        new_positions[2].y += 1;
+                        new_positions[3].x -= 2;
+                    }
+                    Rotation::OneEighty => {
+                        new_positions[0].x -= 1;
+                        new_positions[0].y -= 1;
+                        new_positions[2].x += 1;
+                        new_positions[2].y += 1;
+                        new_positions[3].y += 2;
+                    }
+                    Rotation::TwoHundredSeventy => {
+                        new_positions[0].x -= 1;
+                        new_positions[0].y += 1;
+                        new_positions[2].x += 1;
+                        new_positions[2].y -= 1;
+                        new_positions[3].x += 2;
+                    }
+                }
+            }
+        }
+        new_positions
+    }
+
+    /// Kick offsets to test when rotating from `from` in the given direction, in the order they have to be tried.
+    pub fn kicks(&self, from: Rotation, direction: RotationDirection) -> Vec<(i32, i32)> {
+        let table = match (self, direction) {
+            (Tetromino::O, _) => return vec![(0, 0)],
+            (_, RotationDirection::OneEighty) => return ONE_EIGHTY_KICKS[from as usize].to_vec(),
+            (Tetromino::I, _) => &I_KICKS,
+            _ => &JLSTZ_KICKS,
+        };
+        match direction {
+            RotationDirection::CounterClockwise => {
+                table[from.counter_clockwise() as usize].iter().map(|(x, y)| (-x, -y)).collect()
+            }
+            _ => table[from as usize].to_vec(),
+        }
+    }
+
+    /// Rotates the given positions in the given direction using the Super Rotation System.
+    /// Every kick is tested against the playfield in order, the first one that fits is used.
+    /// Returns the index of the chosen kick (0 being the basic rotation) together with the new positions,
+    /// or `None` if no kick fits and the rotation has to be rejected.
+    pub fn try_srs_rotation(
+        &self,
+        game: &TetrisGame,
+        positions: &[Position],
+        current_rotation: &Rotation,
+        direction: RotationDirection,
+    ) -> Option<(usize, Vec<Position>)> {
+        let rotated = self.try_basic_rotation(positions, current_rotation, direction);
+        self.kicks(*current_rotation, direction)
+            .iter()
+            .enumerate()
+            .map(|(i, (x, y))| {
+                (i, rotated.iter().map(|p| p.offset(*x, *y)).collect::<Vec<_>>())
+            })
+            .find(|(_, kicked)| game.fits(kicked))
+    }
+
+    /// Start positions moved to the middle columns and just above the visible rows of the given playfield
+    pub fn spawn_positions(&self, game: &TetrisGame) -> Vec<Position> {
+        let x = (game.width as i32 - 10) / 2;
+        let y = game.visible_height as i32 - 20;
+        self.start_positions().iter().map(|p| p.offset(x, y)).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tetr {
+    pub positions: Vec<Position>,
+    pub rotation: Rotation,
+    pub tetromino: Tetromino,
+    /// Direction and kick used by the last successful movement, if it was a rotation
+    pub last_kick: Option<(RotationDirection, usize)>,
+}
+
+impl Tetr {
+    pub fn new(tetromino: Tetromino, game: &TetrisGame) -> Self {
+        let positions = tetromino.spawn_positions(game);
+        Tetr {
+            positions,
+            rotation: Rotation::Zero,
+            tetromino,
+            last_kick: None,
+        }
+    }
+
+    /// T-Spin
+    /// The last movement of the T tetromino has to be a rotation and 3 of the 4 corners diagonally adjacent
+    /// to its center have to be occupied (walls and floor count as occupied).
+    /// It is a full t-spin if both corners on the pointing side are occupied, otherwise a mini,
+    /// unless the rotation used the last kick (e.g. TST kicks), which always counts as a full t-spin.
+    /// | Guidelines
+    ///
+    /// 180° rotations aren't part of SRS, their kicks never promote a mini.
+    pub fn spin_kind(&self, game: &TetrisGame) -> Spin {
+        let last_kick = match (self.tetromino, self.last_kick) {
+            (Tetromino::T, Some((direction, kick))) => {
+                direction != RotationDirection::OneEighty && kick == JLSTZ_KICKS[0].len() - 1
+            }
+            _ => return Spin::None,
+        };
+
+        // The center of the T never moves during basic rotation, the 4th block is the one it points to
+        let center = &self.positions[1];
+        let (x, y) = (self.positions[3].x - center.x, self.positions[3].y - center.y);
+        let occupied = |dx: i32, dy: i32| !game.fits(&[center.offset(dx, dy)]);
+        let front = [occupied(x + y, y - x), occupied(x - y, y + x)];
+        let back = [occupied(-x + y, -y - x), occupied(-x - y, -y + x)];
+
+        let corners = front.iter().chain(back.iter()).filter(|&&c| c).count();
+        if corners < 3 {
+            Spin::None
+        } else if front.iter().all(|&c| c) || last_kick {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    /// Ghost Piece
+    /// A representation of where a tetromino will land if allowed to drop into the playfield.
+    /// | Guidelines
+    pub fn ghost_positions(&self, game: &TetrisGame) -> Vec<Position> {
+        let mut ghost = self.positions.clone();
+        loop {
+            let below = ghost.iter().map(|p| p.offset(0, -1)).collect::<Vec<_>>();
+            if !game.fits(&below) {
+                return ghost;
+            }
+            ghost = below;
+        }
+    }
+
+    /// Moves the piece by the given offset if it fits into the playfield, returns whether it was moved
+    pub fn try_shift(&mut self, game: &TetrisGame, x: i32, y: i32) -> bool {
+        let shifted = self.positions.iter().map(|p| p.offset(x, y)).collect::<Vec<_>>();
+        if game.fits(&shifted) {
+            self.positions = shifted;
+            self.last_kick = None;
+            return true;
+        }
+        false
+    }
+
+    /// Rotates the piece in the given direction, returns the used kick index or `None` if the rotation was rejected
+    pub fn spin(&mut self, game: &TetrisGame, direction: RotationDirection) -> Option<usize> {
+        let (kick, positions) = self.tetromino.try_srs_rotation(game, self.positions.as_slice(), &self.rotation, direction)?;
+        self.positions = positions;
+        self.rotation = self.rotation.rotate(direction);
+        self.last_kick = Some((direction, kick));
+        Some(kick)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Spin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+
+    /// T in spawn orientation with its center at the given cell
+    fn t_at(x: i32, y: i32, game: &TetrisGame) -> Tetr {
+        let mut tetr = Tetr::new(Tetromino::T, game);
+        let center = tetr.positions[1].clone();
+        tetr.positions = tetr.positions.iter().map(|p| p.offset(x - center.x, y - center.y)).collect();
+        tetr
+    }
+
+    fn sorted(positions: &[Position]) -> Vec<(i32, i32)> {
+        let mut cells = positions.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn tst_uses_last_kick() {
+        let game = TetrisGame::from_rows(&[
+            "...#......",
+            "..........",
+            "###.######",
+            "##..######",
+            "###.######",
+        ]);
+        let mut tetr = t_at(2, 3, &game);
+
+        assert_eq!(tetr.spin(&game, RotationDirection::CounterClockwise), Some(4));
+        assert_eq!(tetr.rotation, Rotation::TwoHundredSeventy);
+        assert_eq!(sorted(&tetr.positions), vec![(2, 1), (3, 0), (3, 1), (3, 2)]);
+        assert_eq!(tetr.spin_kind(&game), Spin::Full);
+    }
+
+    #[test]
+    fn last_kick_promotes_mini_to_full() {
+        // Only one corner on the pointing side is occupied, which would be a mini without the TST kick
+        let game = TetrisGame::from_rows(&[
+            "...#......",
+            "..........",
+            "###.######",
+            "##..######",
+            "##..######",
+        ]);
+        let mut tetr = t_at(2, 3, &game);
+
+        assert_eq!(tetr.spin(&game, RotationDirection::CounterClockwise), Some(4));
+        assert_eq!(tetr.spin_kind(&game), Spin::Full);
+
+        tetr.last_kick = Some((RotationDirection::CounterClockwise, 0));
+        assert_eq!(tetr.spin_kind(&game), Spin::Mini);
+
+        // 180° rotations have more kicks than SRS, none of them is a TST kick
+        for kick in [4, 5] {
+            tetr.last_kick = Some((RotationDirection::OneEighty, kick));
+            assert_eq!(tetr.spin_kind(&game), Spin::Mini);
+        }
+    }
+
+    #[test]
+    fn i_kicks_off_the_left_wall() {
+        let game = TetrisGame::default();
+        let mut tetr = Tetr::new(Tetromino::I, &game);
+        assert_eq!(tetr.spin(&game, RotationDirection::Clockwise), Some(0));
+        while tetr.try_shift(&game, -1, 0) {}
+        assert_eq!(tetr.positions[0].x, 0);
+
+        // Back to horizontal the basic rotation ends up two columns outside of the wall, the first kick moves it back in
+        assert_eq!(tetr.spin(&game, RotationDirection::CounterClockwise), Some(1));
+        assert_eq!(tetr.rotation, Rotation::Zero);
+        assert!(tetr.positions.iter().all(|p| p.y == tetr.positions[0].y));
+        assert_eq!(sorted(&tetr.positions).iter().map(|p| p.0).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn rotation_without_room_is_rejected() {
+        let mut game = TetrisGame::default();
+        let tetr = Tetr::new(Tetromino::T, &game);
+        for row in game.field.iter_mut() {
+            row.fill(Cell::Garbage);
+        }
+        for p in &tetr.positions {
+            game.field[p.y as usize][p.x as usize] = Cell::Empty;
+        }
+
+        for direction in [RotationDirection::Clockwise, RotationDirection::CounterClockwise, RotationDirection::OneEighty] {
+            let mut rotated = tetr.clone();
+            assert_eq!(rotated.spin(&game, direction), None);
+            assert_eq!(rotated.positions, tetr.positions);
+            assert_eq!(rotated.rotation, Rotation::Zero);
+            assert_eq!(rotated.last_kick, None);
+        }
+    }
+}
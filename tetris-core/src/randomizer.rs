@@ -0,0 +1,349 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::str::FromStr;
+use crate::piece::Tetromino;
+
+const TETROMINOES: [Tetromino; 7] = [Tetromino::I, Tetromino::O, Tetromino::T, Tetromino::S, Tetromino::Z, Tetromino::J, Tetromino::L];
+
+/// Small seedable PRNG (SplitMix64), so the same seed gives the same sequence on every platform
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `0..max`
+    pub fn below(&mut self, max: usize) -> usize {
+        (self.next_u64() % max as u64) as usize
+    }
+}
+
+/// Decides which tetromino comes next
+pub trait Randomizer: Debug + Send + Sync {
+    fn next(&mut self) -> Tetromino;
+
+    /// Copy of the current state, so a game can be snapshotted mid-sequence
+    fn boxed_clone(&self) -> Box<dyn Randomizer>;
+}
+
+impl Clone for Box<dyn Randomizer> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Random Generator
+/// All seven tetrominoes are put into a bag in a random order and drawn one after another,
+/// a new bag is filled once the previous one is empty.
+/// | Guidelines
+///
+/// With more than one copy per bag this becomes the 14-bag (two copies of every tetromino).
+#[derive(Debug, Clone)]
+pub struct Bag {
+    rng: SeededRng,
+    copies: usize,
+    bag: Vec<Tetromino>,
+}
+
+impl Bag {
+    pub fn new(seed: u64, copies: usize) -> Self {
+        Bag {
+            rng: SeededRng::new(seed),
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next(&mut self) -> Tetromino {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend(TETROMINOES);
+            }
+        }
+        let index = self.rng.below(self.bag.len());
+        self.bag.remove(index)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// Every tetromino is chosen independently
+#[derive(Debug, Clone)]
+pub struct PureRandom {
+    rng: SeededRng,
+}
+
+impl PureRandom {
+    pub fn new(seed: u64) -> Self {
+        PureRandom { rng: SeededRng::new(seed) }
+    }
+}
+
+impl Randomizer for PureRandom {
+    fn next(&mut self) -> Tetromino {
+        TETROMINOES[self.rng.below(TETROMINOES.len())]
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// NES Tetris rolls one of 8 values, if it's the dummy value or the same tetromino as last time
+/// it rerolls once from the 7 tetrominoes and takes whatever comes up.
+#[derive(Debug, Clone)]
+pub struct Nes {
+    rng: SeededRng,
+    last: Option<Tetromino>,
+}
+
+impl Nes {
+    pub fn new(seed: u64) -> Self {
+        Nes { rng: SeededRng::new(seed), last: None }
+    }
+}
+
+impl Randomizer for Nes {
+    fn next(&mut self) -> Tetromino {
+        let roll = self.rng.below(TETROMINOES.len() + 1);
+        let tetromino = match TETROMINOES.get(roll) {
+            Some(&t) if Some(t) != self.last => t,
+            _ => TETROMINOES[self.rng.below(TETROMINOES.len())],
+        };
+        self.last = Some(tetromino);
+        tetromino
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// TGM keeps a history of the last 4 tetrominoes and rolls up to 6 times for one that isn't in it,
+/// the last roll is taken if all of them were. The history starts as Z, Z, S, S and the first
+/// tetromino is never S, Z or O.
+#[derive(Debug, Clone)]
+pub struct Tgm {
+    rng: SeededRng,
+    history: [Tetromino; 4],
+    first: bool,
+}
+
+impl Tgm {
+    pub const ROLLS: usize = 6;
+
+    pub fn new(seed: u64) -> Self {
+        Tgm {
+            rng: SeededRng::new(seed),
+            history: [Tetromino::Z, Tetromino::Z, Tetromino::S, Tetromino::S],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for Tgm {
+    fn next(&mut self) -> Tetromino {
+        let tetromino = if self.first {
+            self.first = false;
+            [Tetromino::I, Tetromino::T, Tetromino::J, Tetromino::L][self.rng.below(4)]
+        } else {
+            let mut tetromino = TETROMINOES[self.rng.below(TETROMINOES.len())];
+            for _ in 1..Self::ROLLS {
+                if !self.history.contains(&tetromino) {
+                    break;
+                }
+                tetromino = TETROMINOES[self.rng.below(TETROMINOES.len())];
+            }
+            tetromino
+        };
+        self.history.rotate_left(1);
+        self.history[3] = tetromino;
+        tetromino
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum RandomizerKind {
+    #[default]
+    SevenBag,
+    FourteenBag,
+    Random,
+    Nes,
+    Tgm,
+}
+
+impl RandomizerKind {
+    pub fn build(&self, seed: u64) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::SevenBag => Box::new(Bag::new(seed, 1)),
+            RandomizerKind::FourteenBag => Box::new(Bag::new(seed, 2)),
+            RandomizerKind::Random => Box::new(PureRandom::new(seed)),
+            RandomizerKind::Nes => Box::new(Nes::new(seed)),
+            RandomizerKind::Tgm => Box::new(Tgm::new(seed)),
+        }
+    }
+}
+
+impl FromStr for RandomizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "7-bag" | "bag" => Ok(RandomizerKind::SevenBag),
+            "14-bag" => Ok(RandomizerKind::FourteenBag),
+            "random" => Ok(RandomizerKind::Random),
+            "nes" => Ok(RandomizerKind::Nes),
+            "tgm" => Ok(RandomizerKind::Tgm),
+            _ => Err(format!("unknown randomizer: {s}")),
+        }
+    }
+}
+
+/// Upcoming tetrominoes, refilled from the randomizer
+#[derive(Debug, Clone)]
+pub struct TetroQueue {
+    queue: VecDeque<Tetromino>,
+    randomizer: Box<dyn Randomizer>,
+    seed: u64,
+}
+
+impl Default for TetroQueue {
+    fn default() -> Self {
+        TetroQueue::new(RandomizerKind::default(), 0)
+    }
+}
+
+impl TetroQueue {
+    pub fn new(kind: RandomizerKind, seed: u64) -> Self {
+        TetroQueue {
+            queue: VecDeque::new(),
+            randomizer: kind.build(seed),
+            seed,
+        }
+    }
+
+    /// The seed the randomizer was created with, the same seed always gives the same sequence
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn push(&mut self, tetromino: Tetromino) {
+        self.queue.push_back(tetromino);
+    }
+
+    pub fn pop(&mut self) -> Option<Tetromino> {
+        self.queue.pop_front()
+    }
+
+    /// Takes the next tetromino, asking the randomizer for it if none are known yet
+    pub fn take_next(&mut self) -> Tetromino {
+        self.fill_to(1);
+        self.queue.pop_front().expect("the queue was just filled")
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Tetromino> {
+        self.queue.get(index)
+    }
+
+    /// Refills the queue until at least `lookahead` tetrominoes are known
+    pub fn fill_to(&mut self, lookahead: usize) {
+        while self.len() < lookahead {
+            self.fill_queue();
+        }
+    }
+
+    pub fn fill_queue(&mut self) {
+        let tetromino = self.randomizer.next();
+        self.push(tetromino);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [RandomizerKind; 5] = [
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::Random,
+        RandomizerKind::Nes,
+        RandomizerKind::Tgm,
+    ];
+
+    fn sequence(kind: RandomizerKind, seed: u64, length: usize) -> Vec<Tetromino> {
+        let mut randomizer = kind.build(seed);
+        (0..length).map(|_| randomizer.next()).collect()
+    }
+
+    fn count(tetrominoes: &[Tetromino], tetromino: Tetromino) -> usize {
+        tetrominoes.iter().filter(|&&t| t == tetromino).count()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        for kind in KINDS {
+            for seed in [0, 1, 0xDEAD_BEEF] {
+                assert_eq!(sequence(kind, seed, 500), sequence(kind, seed, 500), "{kind:?} with seed {seed}");
+            }
+            assert_ne!(sequence(kind, 1, 500), sequence(kind, 2, 500), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn bags_hand_out_every_tetromino_once_per_copy() {
+        for (kind, copies) in [(RandomizerKind::SevenBag, 1), (RandomizerKind::FourteenBag, 2)] {
+            let tetrominoes = sequence(kind, 3, 7 * copies * 50);
+            for bag in tetrominoes.chunks(7 * copies) {
+                for tetromino in TETROMINOES {
+                    assert_eq!(count(bag, tetromino), copies, "{kind:?} bag {bag:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_never_starts_with_s_z_or_o() {
+        for seed in 0..200 {
+            let first = sequence(RandomizerKind::Tgm, seed, 1)[0];
+            assert!(![Tetromino::S, Tetromino::Z, Tetromino::O].contains(&first), "seed {seed} started with {first:?}");
+        }
+    }
+
+    #[test]
+    fn nes_rerolls_the_dummy_value() {
+        // The dummy value must not turn into one particular tetromino, so all of them come up about equally often
+        let tetrominoes = sequence(RandomizerKind::Nes, 5, 7000);
+        for tetromino in TETROMINOES {
+            let count = count(&tetrominoes, tetromino);
+            assert!((850..1150).contains(&count), "{tetromino:?} came up {count} times");
+        }
+    }
+}
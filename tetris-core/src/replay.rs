@@ -0,0 +1,620 @@
+use std::fmt::{Display, Formatter};
+use crate::config::{GameConfig, GameMode, LockDownMode};
+use crate::game::{Game, GameEvent, Inputs};
+use crate::randomizer::RandomizerKind;
+
+/// Everything needed to play a game again: the seed, the rules and the buttons held in every tick.
+/// The outcome is stored as well, so a replay can be checked against it without a window.
+///
+/// Binary format, all numbers little endian:
+/// `TRPL`, version (u16), seed (u64), config, tick count (u64),
+/// runs of identical inputs as (inputs (u16), length (varint)) prefixed with their count (varint),
+/// and a result flag (u8) followed by the result if it is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: GameConfig,
+    /// Inputs of every tick, in order
+    pub inputs: Vec<Inputs>,
+    pub result: Option<ReplayResult>,
+}
+
+/// How the recorded game ended up
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReplayResult {
+    pub points: u32,
+    pub lines: u32,
+    pub level: u32,
+    /// See [`crate::TetrisGame::checksum`]
+    pub board: u64,
+}
+
+impl ReplayResult {
+    pub fn of(game: &Game) -> Self {
+        ReplayResult {
+            points: game.score().points,
+            lines: game.score().lines,
+            level: game.score().level,
+            board: game.board().checksum(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    NotAReplay,
+    UnsupportedVersion(u16),
+    /// The data ended in the middle of the replay
+    Truncated,
+    Invalid(&'static str),
+    /// Playing the replay gave a different result than the recorded one
+    Mismatch { expected: ReplayResult, actual: ReplayResult },
+    /// There is no recorded result to compare with
+    NoResult,
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::NotAReplay => write!(f, "not a replay"),
+            ReplayError::UnsupportedVersion(version) => write!(f, "unsupported replay version {version}"),
+            ReplayError::Truncated => write!(f, "replay is truncated"),
+            ReplayError::Invalid(what) => write!(f, "invalid {what} in replay"),
+            ReplayError::Mismatch { expected, actual } => write!(f, "replay ended with {actual:?} instead of {expected:?}"),
+            ReplayError::NoResult => write!(f, "replay has no result to verify"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    const MAGIC: &'static [u8; 4] = b"TRPL";
+    pub const VERSION: u16 = 1;
+    /// Longest replay that gets loaded, about three days at 60 ticks per second, so a broken file
+    /// can't make us allocate whatever it asks for
+    pub const MAX_TICKS: u64 = 1 << 24;
+
+    pub fn new(config: GameConfig, seed: u64) -> Self {
+        Replay { seed, config, inputs: Vec::new(), result: None }
+    }
+
+    /// Starts a recording of the given game, it has to be recorded from its first tick on
+    pub fn record(game: &Game) -> Self {
+        Replay::new(game.config().clone(), game.seed())
+    }
+
+    /// Adds the inputs of the next tick
+    pub fn push(&mut self, inputs: Inputs) {
+        self.inputs.push(inputs);
+    }
+
+    /// Stores how the game ended up, so the replay can be verified later
+    pub fn finish(&mut self, game: &Game) {
+        self.result = Some(ReplayResult::of(game));
+    }
+
+    /// A new game with the recorded seed and rules, ready for the first tick
+    pub fn game(&self) -> Game {
+        Game::new(self.config.clone(), self.seed)
+    }
+
+    /// Runs all recorded ticks, stopping early if the game ends
+    pub fn play(&self) -> Game {
+        let mut game = self.game();
+        for inputs in &self.inputs {
+            if game.over().is_some() {
+                break;
+            }
+            game.tick(*inputs);
+        }
+        game
+    }
+
+    /// Plays the replay and compares the outcome with the recorded one
+    pub fn verify(&self) -> Result<ReplayResult, ReplayError> {
+        let expected = self.result.ok_or(ReplayError::NoResult)?;
+        let actual = ReplayResult::of(&self.play());
+        if actual != expected {
+            return Err(ReplayError::Mismatch { expected, actual });
+        }
+        Ok(actual)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend(Self::VERSION.to_le_bytes());
+        bytes.extend(self.seed.to_le_bytes());
+        write_config(&mut bytes, &self.config);
+
+        bytes.extend((self.inputs.len() as u64).to_le_bytes());
+        let runs = runs(&self.inputs);
+        write_varint(&mut bytes, runs.len() as u64);
+        for (inputs, length) in runs {
+            bytes.extend(inputs.0.to_le_bytes());
+            write_varint(&mut bytes, length);
+        }
+
+        match self.result {
+            Some(result) => {
+                bytes.push(1);
+                bytes.extend(result.points.to_le_bytes());
+                bytes.extend(result.lines.to_le_bytes());
+                bytes.extend(result.level.to_le_bytes());
+                bytes.extend(result.board.to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != Self::MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+        let version = reader.u16()?;
+        if version != Self::VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let seed = reader.u64()?;
+        let config = read_config(&mut reader)?;
+
+        let ticks = reader.u64()?;
+        if ticks > Self::MAX_TICKS {
+            return Err(ReplayError::Invalid("tick count"));
+        }
+        let mut inputs = Vec::new();
+        for _ in 0..reader.varint()? {
+            let held = Inputs(reader.u16()?);
+            let length = reader.varint()?;
+            if (inputs.len() as u64).checked_add(length).is_none_or(|end| end > ticks) {
+                return Err(ReplayError::Invalid("input runs"));
+            }
+            inputs.extend(std::iter::repeat_n(held, length as usize));
+        }
+        if inputs.len() as u64 != ticks {
+            return Err(ReplayError::Invalid("tick count"));
+        }
+
+        let result = match reader.u8()? {
+            0 => None,
+            _ => Some(ReplayResult {
+                points: reader.u32()?,
+                lines: reader.u32()?,
+                level: reader.u32()?,
+                board: reader.u64()?,
+            }),
+        };
+        Ok(Replay { seed, config, inputs, result })
+    }
+
+    /// Human readable dump for debugging, inputs are written as runs of `[inputs, ticks]`
+    pub fn to_json(&self) -> String {
+        let config = &self.config;
+        let optional = |value: Option<f32>| value.map_or("null".to_string(), |v| v.to_string());
+        let inputs = runs(&self.inputs)
+            .iter()
+            .map(|(inputs, length)| format!("[{}, {}]", inputs.0, length))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let result = self.result.map_or("null".to_string(), |r| {
+            format!(
+                "{{\"points\": {}, \"lines\": {}, \"level\": {}, \"board\": \"{:016x}\"}}",
+                r.points, r.lines, r.level, r.board
+            )
+        });
+
+        format!(
+            "{{\n  \"version\": {},\n  \"seed\": {},\n  \"config\": {{\"mode\": \"{:?}\", \"start_level\": {}, \"lock_down\": \"{:?}\", \
+             \"das\": {}, \"arr\": {}, \"das_cut\": {}, \"soft_drop_factor\": {}, \"gravity\": {}, \"preview\": {}, \
+             \"randomizer\": \"{:?}\", \"width\": {}, \"visible_height\": {}, \"buffer_height\": {}, \"are\": {}, \
+             \"line_clear_delay\": {}, \"irs\": {}, \"ihs\": {}, \"tick_rate\": {}}},\n  \"ticks\": {},\n  \"inputs\": [{}],\n  \"result\": {}\n}}\n",
+            Self::VERSION, self.seed,
+            config.mode, config.start_level, config.lock_down, config.das, config.arr, config.das_cut,
+            // JSON has no infinity, it is only used for instant soft drops
+            if config.soft_drop_factor.is_finite() { config.soft_drop_factor.to_string() } else { "null".to_string() },
+            optional(config.gravity), config.preview, config.randomizer, config.width, config.visible_height,
+            config.buffer_height, optional(config.are), optional(config.line_clear_delay), config.irs, config.ihs,
+            config.tick_rate, self.inputs.len(), inputs, result,
+        )
+    }
+}
+
+/// A replay played through once, keeping a copy of the game every [`Timeline::SNAPSHOT_INTERVAL`] ticks,
+/// so any tick can be reached by replaying at most that many ticks from the closest earlier copy.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    replay: Replay,
+    snapshots: Vec<Game>,
+    /// Ticks after which every piece spawned, in order
+    pieces: Vec<u64>,
+    /// Ticks until the game ended or the inputs ran out
+    len: u64,
+}
+
+impl Timeline {
+    pub const SNAPSHOT_INTERVAL: u64 = 120;
+
+    pub fn new(replay: Replay) -> Self {
+        let mut game = replay.game();
+        let mut snapshots = Vec::new();
+        let mut pieces = Vec::new();
+        // Holding spawns the held tetromino too, only a spawn after a lock is a new piece
+        let mut locked = true;
+        for inputs in &replay.inputs {
+            if game.over().is_some() {
+                break;
+            }
+            if game.ticks().is_multiple_of(Self::SNAPSHOT_INTERVAL) {
+                snapshots.push(game.clone());
+            }
+            for event in game.tick(*inputs) {
+                match event {
+                    GameEvent::Locked { .. } => locked = true,
+                    GameEvent::Spawned(_) if locked => {
+                        locked = false;
+                        pieces.push(game.ticks());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if snapshots.is_empty() {
+            snapshots.push(game.clone());
+        }
+        Timeline { len: game.ticks(), replay, snapshots, pieces }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Number of ticks that can be played
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inputs for the given tick, `None` once the replay is over
+    pub fn inputs(&self, tick: u64) -> Option<Inputs> {
+        (tick < self.len).then(|| self.replay.inputs[tick as usize])
+    }
+
+    /// The game after the given number of ticks, clamped to the end of the replay
+    pub fn seek(&self, tick: u64) -> Game {
+        let tick = tick.min(self.len);
+        let index = ((tick / Self::SNAPSHOT_INTERVAL) as usize).min(self.snapshots.len() - 1);
+        let mut game = self.snapshots[index].clone();
+        while game.ticks() < tick {
+            game.tick(self.replay.inputs[game.ticks() as usize]);
+        }
+        game
+    }
+
+    /// Number of pieces spawned in the whole replay
+    pub fn pieces(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Tick right after the given piece spawned, counting from 1
+    pub fn piece_tick(&self, piece: usize) -> Option<u64> {
+        piece.checked_sub(1).and_then(|index| self.pieces.get(index)).copied()
+    }
+
+    /// Number of pieces spawned within the given number of ticks, which is the number of the current piece
+    pub fn piece_at(&self, tick: u64) -> usize {
+        self.pieces.partition_point(|&spawned| spawned <= tick)
+    }
+}
+
+/// Identical inputs following each other, as (inputs, number of ticks)
+fn runs(inputs: &[Inputs]) -> Vec<(Inputs, u64)> {
+    let mut runs: Vec<(Inputs, u64)> = Vec::new();
+    for inputs in inputs {
+        match runs.last_mut() {
+            Some((last, length)) if last == inputs => *length += 1,
+            _ => runs.push((*inputs, 1)),
+        }
+    }
+    runs
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_optional(bytes: &mut Vec<u8>, value: Option<f32>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            bytes.extend(value.to_le_bytes());
+        }
+        None => bytes.push(0),
+    }
+}
+
+fn write_config(bytes: &mut Vec<u8>, config: &GameConfig) {
+    bytes.push(config.mode as u8);
+    bytes.extend(config.start_level.to_le_bytes());
+    bytes.push(config.lock_down as u8);
+    bytes.extend(config.das.to_le_bytes());
+    bytes.extend(config.arr.to_le_bytes());
+    bytes.extend(config.das_cut.to_le_bytes());
+    bytes.extend(config.soft_drop_factor.to_le_bytes());
+    write_optional(bytes, config.gravity);
+    bytes.push(config.preview as u8);
+    bytes.push(config.randomizer as u8);
+    bytes.push(config.width as u8);
+    bytes.push(config.visible_height as u8);
+    bytes.push(config.buffer_height as u8);
+    write_optional(bytes, config.are);
+    write_optional(bytes, config.line_clear_delay);
+    bytes.push(config.irs as u8);
+    bytes.push(config.ihs as u8);
+    bytes.extend(config.tick_rate.to_le_bytes());
+}
+
+fn read_config(reader: &mut Reader) -> Result<GameConfig, ReplayError> {
+    let mode = match reader.u8()? {
+        0 => GameMode::Marathon,
+        1 => GameMode::VariableMarathon,
+        2 => GameMode::Nes,
+        3 => GameMode::Classic,
+        _ => return Err(ReplayError::Invalid("game mode")),
+    };
+    let start_level = reader.u32()?;
+    if !GameConfig::START_LEVELS.contains(&start_level) {
+        return Err(ReplayError::Invalid("start level"));
+    }
+    let lock_down = match reader.u8()? {
+        0 => LockDownMode::Extended,
+        1 => LockDownMode::Infinite,
+        2 => LockDownMode::StepReset,
+        _ => return Err(ReplayError::Invalid("lock down mode")),
+    };
+    let das = reader.f32()?;
+    let arr = reader.f32()?;
+    let das_cut = reader.f32()?;
+    let soft_drop_factor = reader.f32()?;
+    let gravity = reader.optional()?;
+    let preview = reader.u8()? as usize;
+    if !GameConfig::PREVIEWS.contains(&preview) {
+        return Err(ReplayError::Invalid("preview"));
+    }
+    let randomizer = match reader.u8()? {
+        0 => RandomizerKind::SevenBag,
+        1 => RandomizerKind::FourteenBag,
+        2 => RandomizerKind::Random,
+        3 => RandomizerKind::Nes,
+        4 => RandomizerKind::Tgm,
+        _ => return Err(ReplayError::Invalid("randomizer")),
+    };
+    let width = reader.u8()? as usize;
+    let visible_height = reader.u8()? as usize;
+    let buffer_height = reader.u8()? as usize;
+    if !GameConfig::WIDTHS.contains(&width)
+        || !GameConfig::HEIGHTS.contains(&visible_height)
+        || !GameConfig::HEIGHTS.contains(&buffer_height)
+    {
+        return Err(ReplayError::Invalid("board size"));
+    }
+    let are = reader.optional()?;
+    let line_clear_delay = reader.optional()?;
+    let irs = reader.u8()? != 0;
+    let ihs = reader.u8()? != 0;
+    let tick_rate = reader.u32()?;
+    if !GameConfig::TICK_RATES.contains(&tick_rate) {
+        return Err(ReplayError::Invalid("tick rate"));
+    }
+
+    Ok(GameConfig {
+        mode,
+        start_level,
+        lock_down,
+        das,
+        arr,
+        das_cut,
+        soft_drop_factor,
+        gravity,
+        preview,
+        randomizer,
+        width,
+        visible_height,
+        buffer_height,
+        are,
+        line_clear_delay,
+        irs,
+        ihs,
+        tick_rate,
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ReplayError> {
+        if self.bytes.len() < count {
+            return Err(ReplayError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ReplayError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, ReplayError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, ReplayError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, ReplayError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn optional(&mut self) -> Result<Option<f32>, ReplayError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.f32()?)),
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, ReplayError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ReplayError::Invalid("number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game played for a while with a few different buttons, recorded from the start
+    fn recorded(ticks: u64) -> Replay {
+        let mut game = Game::new(GameConfig::default(), 7);
+        let mut replay = Replay::record(&game);
+        for tick in 0..ticks {
+            let inputs = match tick % 40 {
+                0..=3 => Inputs::LEFT,
+                10 => Inputs::ROTATE_CW,
+                20 => Inputs::HOLD,
+                30 => Inputs::HARD_DROP,
+                _ => Inputs::NONE,
+            };
+            game.tick(inputs);
+            replay.push(inputs);
+        }
+        replay.finish(&game);
+        replay
+    }
+
+    /// Everything [`Replay::to_bytes`] writes before the tick count
+    fn header() -> Vec<u8> {
+        let mut bytes = Replay::MAGIC.to_vec();
+        bytes.extend(Replay::VERSION.to_le_bytes());
+        bytes.extend(1u64.to_le_bytes());
+        write_config(&mut bytes, &GameConfig::default());
+        bytes
+    }
+
+    #[test]
+    fn written_replay_loads_and_verifies() {
+        let replay = recorded(2000);
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.verify(), Ok(replay.result.unwrap()));
+    }
+
+    #[test]
+    fn changed_result_is_a_mismatch() {
+        let mut replay = recorded(2000);
+        let actual = replay.result.unwrap();
+        let expected = ReplayResult { points: actual.points + 100, ..actual };
+        replay.result = Some(expected);
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.verify(), Err(ReplayError::Mismatch { expected, actual }));
+    }
+
+    #[test]
+    fn truncated_replay_is_rejected() {
+        let bytes = recorded(200).to_bytes();
+        for length in [0, 3, 10, bytes.len() - 1] {
+            assert!(Replay::from_bytes(&bytes[..length]).is_err(), "{length} bytes");
+        }
+        assert_eq!(Replay::from_bytes(&bytes[..bytes.len() - 1]), Err(ReplayError::Truncated));
+    }
+
+    #[test]
+    fn huge_tick_count_is_rejected() {
+        let mut bytes = header();
+        bytes.extend(u64::MAX.to_le_bytes());
+        write_varint(&mut bytes, 1);
+        bytes.extend(Inputs::NONE.0.to_le_bytes());
+        write_varint(&mut bytes, u64::MAX);
+        bytes.push(0);
+        assert_eq!(Replay::from_bytes(&bytes), Err(ReplayError::Invalid("tick count")));
+    }
+
+    #[test]
+    fn runs_longer_than_the_replay_are_rejected() {
+        // One tick, then a run so long that adding it to the inputs read so far overflows
+        let mut bytes = header();
+        bytes.extend(1u64.to_le_bytes());
+        write_varint(&mut bytes, 2);
+        bytes.extend(Inputs::LEFT.0.to_le_bytes());
+        write_varint(&mut bytes, 1);
+        bytes.extend(Inputs::NONE.0.to_le_bytes());
+        write_varint(&mut bytes, u64::MAX);
+        bytes.push(0);
+        assert_eq!(Replay::from_bytes(&bytes), Err(ReplayError::Invalid("input runs")));
+
+        let mut bytes = header();
+        bytes.extend(1u64.to_le_bytes());
+        write_varint(&mut bytes, 1);
+        bytes.extend(Inputs::LEFT.0.to_le_bytes());
+        write_varint(&mut bytes, 2);
+        bytes.push(0);
+        assert_eq!(Replay::from_bytes(&bytes), Err(ReplayError::Invalid("input runs")));
+    }
+
+    #[test]
+    fn holding_does_not_count_as_a_new_piece() {
+        let mut replay = Replay::new(GameConfig::default(), 3);
+        for inputs in [Inputs::NONE, Inputs::HOLD, Inputs::NONE, Inputs::HARD_DROP, Inputs::NONE, Inputs::HOLD] {
+            replay.push(inputs);
+        }
+        let timeline = Timeline::new(replay);
+        // Spawned in the first tick and right after the hard drop, both holds only swap the tetromino
+        assert_eq!(timeline.pieces(), 2);
+        assert_eq!(timeline.piece_tick(1), Some(1));
+        assert_eq!(timeline.piece_tick(2), Some(4));
+        assert_eq!(timeline.piece_at(3), 1);
+        assert_eq!(timeline.piece_at(6), 2);
+    }
+
+    #[test]
+    fn config_out_of_range_is_rejected() {
+        let configs = [
+            (GameConfig { start_level: u32::MAX, ..GameConfig::default() }, "start level"),
+            (GameConfig { preview: 0, ..GameConfig::default() }, "preview"),
+            (GameConfig { width: 2, ..GameConfig::default() }, "board size"),
+            (GameConfig { visible_height: 200, ..GameConfig::default() }, "board size"),
+            (GameConfig { buffer_height: 0, ..GameConfig::default() }, "board size"),
+            (GameConfig { tick_rate: 0, ..GameConfig::default() }, "tick rate"),
+        ];
+        for (config, what) in configs {
+            let bytes = Replay::new(config, 1).to_bytes();
+            assert_eq!(Replay::from_bytes(&bytes), Err(ReplayError::Invalid(what)));
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::piece::Spin;
+
+/// How points are awarded, when the level goes up and how fast pieces fall
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ScoringSystem {
+    /// Guideline marathon, every level takes 10 lines
+    #[default]
+    FixedGoal,
+    /// Guideline variable goal, level n takes 5n awarded lines, where harder clears award more
+    VariableGoal,
+    /// NES, 40/100/300/1200 × (level + 1) points, the first level up happens at the transition level
+    Nes,
+    /// Only counts cleared lines, one level per 10 lines
+    Classic,
+}
+
+impl ScoringSystem {
+    /// Whether t-spins, back-to-back, combos, perfect clears and drop points count
+    pub fn is_guideline(&self) -> bool {
+        matches!(self, ScoringSystem::FixedGoal | ScoringSystem::VariableGoal)
+    }
+
+    /// Points for a lock before they get multiplied with the level
+    pub fn points(&self, lines: u32, spin: Spin) -> u32 {
+        match self {
+            ScoringSystem::FixedGoal | ScoringSystem::VariableGoal => match (spin, lines) {
+                (Spin::None, 0) => 0,
+                (Spin::None, 1) => 100,
+                (Spin::None, 2) => 300,
+                (Spin::None, 3) => 500,
+                (Spin::None, _) => 800,
+                (Spin::Mini, 0) => 100,
+                (Spin::Mini, 1) => 200,
+                (Spin::Mini, _) => 400,
+                (Spin::Full, 0) => 400,
+                (Spin::Full, 1) => 800,
+                (Spin::Full, 2) => 1200,
+                (Spin::Full, _) => 1600,
+            },
+            ScoringSystem::Nes => match lines {
+                0 => 0,
+                1 => 40,
+                2 => 100,
+                3 => 300,
+                _ => 1200,
+            },
+            ScoringSystem::Classic => lines,
+        }
+    }
+
+    /// Level multiplier for the points, guideline levels start at 1 while we count from 0
+    pub fn multiplier(&self, level: u32) -> u32 {
+        match self {
+            ScoringSystem::Classic => 1,
+            _ => level + 1,
+        }
+    }
+
+    /// Lines a lock counts towards the goal
+    pub fn goal_lines(&self, lines: u32, spin: Spin) -> u32 {
+        match self {
+            ScoringSystem::VariableGoal => self.points(lines, spin) / 100,
+            _ => lines,
+        }
+    }
+
+    /// Lines needed to get from `level` to the next one
+    pub fn goal(&self, level: u32, start_level: u32) -> u32 {
+        match self {
+            ScoringSystem::VariableGoal => 5 * (level + 1),
+            ScoringSystem::Nes if level == start_level => {
+                (start_level * 10 + 10).min((start_level * 10).saturating_sub(50).max(100))
+            }
+            _ => 10,
+        }
+    }
+
+    /// Gravity in G, the rows a piece falls per frame at 60 frames per second
+    pub fn gravity(&self, level: u32) -> f32 {
+        1.0 / (self.seconds_per_row(level) * 60.0)
+    }
+
+    /// Seconds it takes a piece to fall one row
+    pub fn seconds_per_row(&self, level: u32) -> f32 {
+        match self {
+            // (0.8 - ((level - 1) * 0.007))^(level - 1) | Guidelines
+            ScoringSystem::FixedGoal | ScoringSystem::VariableGoal => match level {
+                0 => 1.0,
+                1 => 0.79300,
+                2 => 0.61780,
+                3 => 0.47273,
+                4 => 0.35520,
+                5 => 0.26200,
+                6 => 0.18968,
+                7 => 0.13473,
+                8 => 0.09388,
+                9 => 0.06415,
+                10 => 0.04298,
+                11 => 0.02822,
+                12 => 0.01815,
+                13 => 0.01144,
+                14 => 0.00706,
+                15 => 0.00426,
+                16 => 0.00252,
+                17 => 0.00146,
+                18 => 0.00082,
+                _ => 0.00046,
+            },
+            // Frames per row at 60.0988 fps
+            ScoringSystem::Nes => {
+                let frames = match level {
+                    0 => 48,
+                    1 => 43,
+                    2 => 38,
+                    3 => 33,
+                    4 => 28,
+                    5 => 23,
+                    6 => 18,
+                    7 => 13,
+                    8 => 8,
+                    9 => 6,
+                    10..=12 => 5,
+                    13..=15 => 4,
+                    16..=18 => 3,
+                    19..=28 => 2,
+                    _ => 1,
+                };
+                frames as f32 / 60.0988
+            }
+            // Game Boy frames per row at 59.73 fps
+            ScoringSystem::Classic => {
+                let frames = match level {
+                    0 => 53,
+                    1 => 49,
+                    2 => 45,
+                    3 => 41,
+                    4 => 37,
+                    5 => 33,
+                    6 => 28,
+                    7 => 22,
+                    8 => 17,
+                    9 => 11,
+                    10 => 10,
+                    11 => 9,
+                    12 => 8,
+                    13 => 7,
+                    14 | 15 => 6,
+                    16 | 17 => 5,
+                    18 | 19 => 4,
+                    _ => 3,
+                };
+                frames as f32 / 59.73
+            }
+        }
+    }
+}
+
+impl FromStr for ScoringSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" | "fixed-goal" => Ok(ScoringSystem::FixedGoal),
+            "variable" | "variable-goal" => Ok(ScoringSystem::VariableGoal),
+            "nes" => Ok(ScoringSystem::Nes),
+            "classic" => Ok(ScoringSystem::Classic),
+            _ => Err(format!("unknown scoring system: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClearKind {
+    Single,
+    Double,
+    Triple,
+    Tetris,
+}
+
+impl ClearKind {
+    pub fn from_lines(lines: usize) -> Option<ClearKind> {
+        match lines {
+            0 => None,
+            1 => Some(ClearKind::Single),
+            2 => Some(ClearKind::Double),
+            3 => Some(ClearKind::Triple),
+            _ => Some(ClearKind::Tetris),
+        }
+    }
+}
+
+/// Reported for every lock that awards points for how it was placed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Scored {
+    pub lines: u32,
+    pub spin: Spin,
+    pub points: u32,
+    pub back_to_back: bool,
+    pub combo: u32,
+    pub perfect_clear: bool,
+}
+
+impl Display for Scored {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.back_to_back {
+            write!(f, "B2B ")?;
+        }
+        match self.spin {
+            Spin::Full => write!(f, "T-SPIN ")?,
+            Spin::Mini => write!(f, "T-SPIN MINI ")?,
+            Spin::None => {}
+        }
+        match self.lines {
+            0 => {}
+            1 => write!(f, "SINGLE")?,
+            2 => write!(f, "DOUBLE")?,
+            3 => write!(f, "TRIPLE")?,
+            _ => write!(f, "TETRIS")?,
+        }
+        if self.combo > 0 {
+            write!(f, "\n{} COMBO", self.combo)?;
+        }
+        if self.perfect_clear {
+            write!(f, "\nPERFECT CLEAR")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Score {
+    /// Lines counted towards the goal of the current level
+    pub score: u32,
+    pub level: u32,
+    pub start_level: u32,
+    pub lines: u32,
+    pub points: u32,
+    /// Number of consecutive locks that cleared lines, minus one
+    pub combo: Option<u32>,
+    /// Whether the last line clear was a tetris or a t-spin
+    pub back_to_back: bool,
+    pub system: ScoringSystem,
+}
+
+impl Score {
+    pub fn new(system: ScoringSystem, start_level: u32) -> Self {
+        Score {
+            level: start_level,
+            start_level,
+            system,
+            ..Default::default()
+        }
+    }
+
+    /// Soft drops award 1 point per row, hard drops 2
+    /// | Guidelines
+    pub fn award_drop(&mut self, rows: u32, hard: bool) {
+        if self.system.is_guideline() {
+            self.points += if hard { 2 * rows } else { rows };
+        } else if self.system == ScoringSystem::Nes && !hard {
+            self.points += rows;
+        }
+    }
+
+    /// Scoring
+    /// Line clears and t-spins are multiplied by the level, tetrises and t-spins that clear lines
+    /// following each other get a back-to-back bonus of 1.5 times, consecutive line clears award 50 times the combo count
+    /// and clearing the whole field awards an extra perfect clear bonus.
+    /// | Guidelines
+    ///
+    /// Scoring systems that aren't guideline based only award their points table.
+    pub fn award_lock(&mut self, lines: u32, spin: Spin, perfect_clear: bool) -> Option<Scored> {
+        let guideline = self.system.is_guideline();
+        let spin = if guideline { spin } else { Spin::None };
+        let level = self.system.multiplier(self.level);
+
+        let difficult = guideline && (lines >= 4 || (spin != Spin::None && lines > 0));
+        let back_to_back = difficult && self.back_to_back;
+        if lines > 0 {
+            self.back_to_back = difficult;
+            self.combo = Some(self.combo.map_or(0, |c| c + 1));
+        } else {
+            self.combo = None;
+        }
+        let combo = if guideline { self.combo.unwrap_or(0) } else { 0 };
+        let perfect_clear = guideline && perfect_clear;
+
+        let mut points = self.system.points(lines, spin) * level;
+        if back_to_back {
+            points = points * 3 / 2;
+        }
+        points += 50 * combo * level;
+        if perfect_clear {
+            points += match lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if back_to_back => 3200,
+                _ => 2000,
+            } * level;
+        }
+
+        self.points += points;
+        if points == 0 {
+            return None;
+        }
+        Some(Scored { lines, spin, points, back_to_back, combo, perfect_clear })
+    }
+
+    /// Counts the lock towards the goal, returns whether the level went up
+    pub fn increase(&mut self, cleared_lines: u32, spin: Spin) -> bool {
+        self.lines += cleared_lines;
+        self.score += self.system.goal_lines(cleared_lines, spin);
+
+        let mut level_up = false;
+        while self.score >= self.goal() {
+            self.score -= self.goal();
+            self.level += 1;
+            level_up = true;
+        }
+        level_up
+    }
+
+    pub fn goal(&self) -> u32 {
+        self.system.goal(self.level, self.start_level)
+    }
+
+    pub fn gravity(&self) -> f32 {
+        self.system.gravity(self.level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_locks_award_their_table_points_times_the_level() {
+        // (system, level, lines, spin, perfect clear, points)
+        let cases = [
+            (ScoringSystem::FixedGoal, 0, 0, Spin::None, false, None),
+            (ScoringSystem::FixedGoal, 0, 1, Spin::None, false, Some(100)),
+            (ScoringSystem::FixedGoal, 0, 4, Spin::None, false, Some(800)),
+            (ScoringSystem::FixedGoal, 0, 0, Spin::Mini, false, Some(100)),
+            (ScoringSystem::FixedGoal, 0, 1, Spin::Mini, false, Some(200)),
+            (ScoringSystem::FixedGoal, 0, 0, Spin::Full, false, Some(400)),
+            (ScoringSystem::FixedGoal, 0, 2, Spin::Full, false, Some(1200)),
+            (ScoringSystem::FixedGoal, 4, 3, Spin::None, false, Some(2500)),
+            (ScoringSystem::VariableGoal, 1, 3, Spin::Full, false, Some(3200)),
+            (ScoringSystem::FixedGoal, 0, 1, Spin::None, true, Some(900)),
+            (ScoringSystem::FixedGoal, 1, 4, Spin::None, true, Some(5600)),
+            (ScoringSystem::Nes, 9, 4, Spin::None, false, Some(12000)),
+            // NES and classic know neither t-spins nor perfect clears
+            (ScoringSystem::Nes, 0, 1, Spin::Full, true, Some(40)),
+            (ScoringSystem::Nes, 0, 0, Spin::Full, false, None),
+            (ScoringSystem::Classic, 5, 2, Spin::None, false, Some(2)),
+        ];
+        for (system, level, lines, spin, perfect_clear, points) in cases {
+            let mut score = Score::new(system, level);
+            let scored = score.award_lock(lines, spin, perfect_clear);
+            assert_eq!(scored.map(|s| s.points), points, "{system:?} level {level}, {lines} lines, {spin:?}");
+            assert_eq!(score.points, points.unwrap_or(0));
+        }
+    }
+
+    #[test]
+    fn back_to_back_and_combos_follow_the_previous_locks() {
+        // (lines, spin, perfect clear, points, back-to-back, combo) of locks following each other at level 0
+        let locks = [
+            (4, Spin::None, false, Some(800), false, 0),
+            (4, Spin::None, false, Some(1250), true, 1),
+            (2, Spin::Full, false, Some(1900), true, 2),
+            // A single breaks the back-to-back chain but continues the combo
+            (1, Spin::None, false, Some(250), false, 3),
+            (0, Spin::None, false, None, false, 0),
+            (4, Spin::None, false, Some(800), false, 0),
+            // A t-spin without lines ends the combo, but keeps the back-to-back chain going
+            (0, Spin::Full, false, Some(400), false, 0),
+            (4, Spin::None, true, Some(1200 + 3200), true, 0),
+        ];
+        let mut score = Score::new(ScoringSystem::FixedGoal, 0);
+        let mut total = 0;
+        for (i, (lines, spin, perfect_clear, points, back_to_back, combo)) in locks.into_iter().enumerate() {
+            let scored = score.award_lock(lines, spin, perfect_clear);
+            assert_eq!(scored.map(|s| s.points), points, "lock {i}");
+            if let Some(scored) = scored {
+                assert_eq!((scored.back_to_back, scored.combo), (back_to_back, combo), "lock {i}");
+            }
+            total += points.unwrap_or(0);
+        }
+        assert_eq!(score.points, total);
+    }
+
+    #[test]
+    fn scored_shows_every_bonus() {
+        let scored = |lines, spin, back_to_back, combo, perfect_clear| {
+            Scored { lines, spin, points: 0, back_to_back, combo, perfect_clear }.to_string()
+        };
+        assert_eq!(scored(4, Spin::None, false, 0, false), "TETRIS");
+        assert_eq!(scored(1, Spin::Mini, false, 0, false), "T-SPIN MINI SINGLE");
+        assert_eq!(scored(2, Spin::Full, true, 3, true), "B2B T-SPIN DOUBLE\n3 COMBO\nPERFECT CLEAR");
+    }
+}